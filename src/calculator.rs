@@ -1,22 +1,317 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     mem,
-    rc::Rc,
+    sync::{Arc, OnceLock},
 };
 
 use priority_queue::DoublePriorityQueue;
 
-use crate::{Count, Recipe, Stack};
+use crate::{
+    optimizer::Optimizer, rates, ChanceMode, Count, CraftLimit, Craftable, Diagnostic, Objective,
+    ParseError, Plan, PlanError, RatePlan, Recipe, RecipeError, Stack,
+};
+
+/// The largest count that [`Calculator::max_craftable()`] looks for, which keeps the search short
+/// when there are huge amounts of resources.
+const MAX_CRAFTABLE_SEARCH: Count = u32::MAX as Count;
+
+/// The way the calculator chooses between multiple recipes that produce the same item.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum RecipeStrategy {
+    /// Use the recipe that requires the fewest raw materials per item produced.
+    #[default]
+    FewestRawMaterials,
+    /// Use the recipe that requires the fewest distinct crafting steps.
+    FewestSteps,
+    /// Use the recipes that are part of the cheapest plan for all of the targets at once,
+    /// according to the objective. The cheapest plan is found with a linear program over every
+    /// recipe that could be involved, which also accounts for byproducts and the available
+    /// resources. Items that aren't part of that plan fall back to
+    /// [`RecipeStrategy::FewestRawMaterials`].
+    Optimal(Objective),
+    /// Use the recipe that was added first.
+    First,
+}
+
+/// What the calculator does when every recipe it could use for an item needs an item that is
+/// already being crafted further up the recipe tree.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum CyclePolicy {
+    /// Break the cycle by treating the item as a raw material.
+    #[default]
+    TreatAsRaw,
+    /// Fail with [`PlanError::Cycle`] until the cycle has been resolved.
+    Fail,
+}
+
+/// The recipes chosen for every item that needs to be crafted to reach the targets.
+#[derive(Clone, Debug, Default)]
+struct Resolution {
+    recipes: HashMap<String, Arc<Recipe>>,
+    cycles: Vec<Vec<String>>,
+}
+
+/// Decides which of the known recipes may be used.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RecipeFilter<'c> {
+    raw_items: &'c BTreeSet<String>,
+    methods: Option<&'c BTreeSet<String>>,
+}
+
+impl RecipeFilter<'_> {
+    /// Whether `recipe` may be used to craft `item`.
+    pub(crate) fn allows(&self, item: &str, recipe: &Recipe) -> bool {
+        let method_available = match self.methods {
+            Some(methods) => methods.contains(recipe.method()),
+            None => true,
+        };
+        method_available && !self.raw_items.contains(item)
+    }
+}
+
+/// Chooses between the recipes for an item according to a [`RecipeStrategy`].
+#[derive(Debug)]
+struct RecipeChooser<'c> {
+    recipes: &'c BTreeMap<String, Vec<Arc<Recipe>>>,
+    preferred: &'c BTreeMap<String, usize>,
+    filter: RecipeFilter<'c>,
+    strategy: RecipeStrategy,
+    costs: HashMap<&'c str, f64>,
+    usage: HashMap<String, Vec<f64>>,
+}
+
+impl<'c> RecipeChooser<'c> {
+    fn new(
+        recipes: &'c BTreeMap<String, Vec<Arc<Recipe>>>,
+        preferred: &'c BTreeMap<String, usize>,
+        filter: RecipeFilter<'c>,
+        strategy: RecipeStrategy,
+    ) -> Self {
+        let mut chooser = Self {
+            recipes,
+            preferred,
+            filter,
+            strategy,
+            costs: HashMap::new(),
+            usage: HashMap::new(),
+        };
+        if strategy != RecipeStrategy::First {
+            chooser.calculate_costs();
+        }
+        chooser
+    }
+
+    /// Prefers the recipes with the highest `usage`, which lists how often each recipe is used in
+    /// an optimal plan.
+    fn with_usage(mut self, usage: HashMap<String, Vec<f64>>) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Calculates the cost of a single unit of every item that has a recipe. Costs start out
+    /// infinite and are lowered until they stop changing so that recipes which loop back on
+    /// themselves are never cheaper than the recipes which lead out of the loop.
+    fn calculate_costs(&mut self) {
+        // Items without any recipes that may be used don't get a cost, so they cost the same as
+        // other raw materials.
+        self.costs = self
+            .recipes
+            .iter()
+            .filter(|(item, recipes)| {
+                recipes
+                    .iter()
+                    .any(|recipe| self.filter.allows(item, recipe))
+            })
+            .map(|(item, _)| (item.as_str(), f64::INFINITY))
+            .collect();
+        for _ in 0..=self.recipes.len() {
+            let mut changed = false;
+            for (item, recipes) in self.recipes {
+                if !self.costs.contains_key(item.as_str()) {
+                    continue;
+                }
+                let allowed = |recipe: &&Arc<Recipe>| self.filter.allows(item, recipe);
+                let preferred = self.preferred.get(item).and_then(|&idx| recipes.get(idx));
+                let cost = match preferred.filter(allowed) {
+                    Some(recipe) => self.recipe_cost(recipe),
+                    None => recipes
+                        .iter()
+                        .filter(allowed)
+                        .map(|recipe| self.recipe_cost(recipe))
+                        .fold(f64::INFINITY, f64::min),
+                };
+                let known = self.costs.get_mut(item.as_str()).unwrap();
+                if cost < *known {
+                    *known = cost;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// The cost of a single unit of `item`.
+    fn item_cost(&self, item: &str) -> f64 {
+        match self.costs.get(item) {
+            Some(&cost) => cost,
+            None if self.strategy == RecipeStrategy::FewestSteps => 0.0,
+            None => 1.0,
+        }
+    }
+
+    /// The cost of a single unit of the result of `recipe`.
+    fn recipe_cost(&self, recipe: &Recipe) -> f64 {
+        match self.strategy {
+            RecipeStrategy::FewestSteps => {
+                1.0 + recipe
+                    .ingredients()
+                    .iter()
+                    .map(|ingredient| self.item_cost(ingredient.item()))
+                    .sum::<f64>()
+            }
+            _ => {
+                let ingredients = recipe
+                    .ingredients()
+                    .iter()
+                    .map(|ingredient| ingredient.count() as f64 * self.item_cost(ingredient.item()))
+                    .sum::<f64>();
+                let tools = recipe
+                    .tools()
+                    .iter()
+                    .map(|tool| {
+                        tool.uses() as f64 / tool.durability() as f64 * self.item_cost(tool.item())
+                    })
+                    .sum::<f64>();
+                let result = recipe.result();
+                (ingredients + tools)
+                    / (result.count() as f64 * recipe.chance(result.item()).probability())
+            }
+        }
+    }
+
+    /// Whether `recipe` converts back from an ingredient which can only be made from `item`, like
+    /// turning a block back into ingots.
+    fn is_reverse_conversion(&self, item: &str, recipe: &Recipe) -> bool {
+        recipe.ingredients().iter().any(|ingredient| {
+            self.recipes
+                .get(ingredient.item())
+                .filter(|recipes| !recipes.is_empty())
+                .is_some_and(|recipes| {
+                    recipes.iter().all(|recipe| {
+                        recipe
+                            .ingredients()
+                            .iter()
+                            .any(|ingredient| ingredient.item() == item)
+                    })
+                })
+        })
+    }
+
+    /// The recipes for `item` from most to least preferred. Reverse conversions are only tried
+    /// once every other recipe has been tried, unless they have been pinned or they are part of
+    /// an optimal plan.
+    fn candidates(&self, item: &str) -> Vec<&'c Arc<Recipe>> {
+        let Some(recipes) = self.recipes.get(item) else {
+            return vec![];
+        };
+        let mut candidates = (0..recipes.len())
+            .filter(|&idx| self.filter.allows(item, &recipes[idx]))
+            .collect::<Vec<_>>();
+        if self.strategy != RecipeStrategy::First {
+            candidates.sort_by(|&a, &b| {
+                self.recipe_cost(&recipes[a])
+                    .total_cmp(&self.recipe_cost(&recipes[b]))
+            });
+        }
+        candidates.sort_by_key(|&idx| self.is_reverse_conversion(item, &recipes[idx]));
+        if let Some(usage) = self.usage.get(item) {
+            candidates.sort_by(|&a, &b| usage[b].total_cmp(&usage[a]));
+        }
+        if let Some(&idx) = self.preferred.get(item) {
+            if let Some(pos) = candidates.iter().position(|&candidate| candidate == idx) {
+                candidates.remove(pos);
+                candidates.insert(0, idx);
+            }
+        }
+        candidates.into_iter().map(|idx| &recipes[idx]).collect()
+    }
+
+    /// Chooses a recipe for each item needed to craft `targets`. Recipes which need an item that
+    /// is already being crafted further up the tree are skipped in favor of the next best
+    /// recipe. If there is no such recipe, the items involved are recorded as a cycle and the
+    /// item is left without a recipe.
+    fn resolve<'t>(&self, targets: impl IntoIterator<Item = &'t str>) -> Resolution {
+        let mut resolution = Resolution::default();
+        let mut visited = HashSet::new();
+        let mut path = vec![];
+        for target in targets {
+            self.resolve_item(target, &mut visited, &mut path, &mut resolution);
+        }
+        resolution
+    }
+
+    fn resolve_item(
+        &self,
+        item: &str,
+        visited: &mut HashSet<String>,
+        path: &mut Vec<String>,
+        resolution: &mut Resolution,
+    ) {
+        if !visited.insert(item.to_string()) {
+            return;
+        }
+        path.push(item.to_string());
+        let candidates = self.candidates(item);
+        let on_path = |item: &str| path.iter().any(|step| step == item);
+        match candidates
+            .iter()
+            .find(|recipe| !recipe.requirements().any(on_path))
+        {
+            Some(&recipe) => {
+                resolution
+                    .recipes
+                    .insert(item.to_string(), Arc::clone(recipe));
+                for requirement in recipe.requirements() {
+                    self.resolve_item(requirement, visited, path, resolution);
+                }
+            }
+            None => {
+                if let Some(start) = candidates.first().and_then(|recipe| {
+                    recipe
+                        .requirements()
+                        .find_map(|item| path.iter().position(|step| step == item))
+                }) {
+                    resolution.cycles.push(path[start..].to_vec());
+                }
+            }
+        }
+        path.pop();
+    }
+}
 
 /// The actual calculator.
+///
+/// A calculator can be shared between threads. Planning only needs a shared reference, so plans
+/// for different targets can be made at the same time with [`Calculator::plan_for()`].
 #[derive(Clone, Debug)]
 pub struct Calculator {
-    recipes: HashMap<String, Rc<Recipe>>,
-    target: Stack,
-    initial_materials: HashMap<String, Count>,
-    materials: HashMap<String, Count>,
-    crafted_materials: HashMap<String, Count>,
-    steps: Vec<(Rc<Recipe>, Count)>,
+    recipes: BTreeMap<String, Vec<Arc<Recipe>>>,
+    uses: HashMap<String, Vec<Arc<Recipe>>>,
+    strategy: RecipeStrategy,
+    preferred: BTreeMap<String, usize>,
+    raw_items: BTreeSet<String>,
+    methods: Option<BTreeSet<String>>,
+    raw_costs: BTreeMap<String, f64>,
+    cycle_policy: CyclePolicy,
+    targets: Vec<Stack>,
+    initial_materials: BTreeMap<String, Count>,
+    worn_tools: BTreeMap<String, Count>,
+    parallel_machines: Count,
+    chance_mode: ChanceMode,
+    resolution: OnceLock<Resolution>,
+    plan: OnceLock<Result<Plan, PlanError>>,
 }
 
 impl Calculator {
@@ -27,27 +322,299 @@ impl Calculator {
 
     /// Creates a calculator that knows about the given recipes.
     pub fn with_recipes(recipes: HashMap<String, Recipe>) -> Self {
-        Self {
+        let mut calculator = Self {
             recipes: recipes
                 .into_iter()
-                .map(|(output, recipe)| (output, Rc::new(recipe)))
+                .map(|(output, recipe)| (output, vec![Arc::new(recipe)]))
                 .collect(),
-            target: Stack::new("Air", 1),
+            uses: Default::default(),
+            strategy: Default::default(),
+            preferred: Default::default(),
+            raw_items: Default::default(),
+            methods: Default::default(),
+            raw_costs: Default::default(),
+            cycle_policy: Default::default(),
+            targets: Default::default(),
             initial_materials: Default::default(),
-            materials: Default::default(),
-            crafted_materials: Default::default(),
-            steps: Default::default(),
+            worn_tools: Default::default(),
+            parallel_machines: 1,
+            chance_mode: Default::default(),
+            resolution: Default::default(),
+            plan: Default::default(),
+        };
+        for recipe in calculator
+            .recipes
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            calculator.index_uses(&recipe);
         }
+        calculator
     }
 
-    /// Gets the recipes that the calculator knows about.
+    /// Gets the recipes that the calculator knows about, sorted by the item they produce and then
+    /// in the order they were added.
     pub fn recipes(&self) -> impl Iterator<Item = &Recipe> + '_ {
-        self.recipes.values().map(Rc::as_ref)
+        self.recipes.values().flatten().map(Arc::as_ref)
+    }
+
+    /// Gets the recipes that produce `item`, in the order they were added.
+    pub fn recipes_for(&self, item: &str) -> impl Iterator<Item = &Recipe> + '_ {
+        self.recipes
+            .get(item)
+            .into_iter()
+            .flatten()
+            .map(Arc::as_ref)
+    }
+
+    /// Gets the recipes that need `item`, whether they use it up or not.
+    pub fn uses_of(&self, item: &str) -> impl Iterator<Item = &Recipe> + '_ {
+        self.uses.get(item).into_iter().flatten().map(Arc::as_ref)
+    }
+
+    /// Gets every recipe that `item` eventually feeds into: the recipes that need `item`, the
+    /// recipes that need their results, and so on. Recipes are listed closest to `item` first and
+    /// each recipe is only listed once.
+    pub fn all_uses_of(&self, item: &str) -> Vec<&Recipe> {
+        let mut uses = Vec::<&Recipe>::new();
+        let mut seen_items = HashSet::from([item]);
+        let mut to_visit = VecDeque::from([item]);
+        while let Some(item) = to_visit.pop_front() {
+            for recipe in self.uses_of(item) {
+                if uses.iter().any(|&known| std::ptr::eq(known, recipe)) {
+                    continue;
+                }
+                uses.push(recipe);
+                for output in recipe.outputs() {
+                    if seen_items.insert(output.item()) {
+                        to_visit.push_back(output.item());
+                    }
+                }
+            }
+        }
+        uses
+    }
+
+    /// Adds `recipe` to the index of the recipes that need each item.
+    fn index_uses(&mut self, recipe: &Arc<Recipe>) {
+        for item in recipe.requirements() {
+            let uses = self.uses.entry(item.to_string()).or_default();
+            if !uses.iter().any(|known| Arc::ptr_eq(known, recipe)) {
+                uses.push(Arc::clone(recipe));
+            }
+        }
+    }
+
+    /// Removes `recipe` from the index of the recipes that need each item.
+    fn unindex_uses(&mut self, recipe: &Arc<Recipe>) {
+        for item in recipe.requirements() {
+            if let Some(uses) = self.uses.get_mut(item) {
+                uses.retain(|known| !Arc::ptr_eq(known, recipe));
+                if uses.is_empty() {
+                    self.uses.remove(item);
+                }
+            }
+        }
+    }
+
+    /// Gets the strategy used to choose between recipes that produce the same item.
+    pub fn strategy(&self) -> RecipeStrategy {
+        self.strategy
+    }
+
+    /// Sets the strategy used to choose between recipes that produce the same item.
+    pub fn set_strategy(&mut self, strategy: RecipeStrategy) {
+        self.strategy = strategy;
+        self.invalidate_recipes();
+    }
+
+    /// Gets the index into [`self.recipes_for(item)`] of the recipe that has been pinned for
+    /// `item`, if any.
+    ///
+    /// [`self.recipes_for(item)`]: #method.recipes_for
+    pub fn preferred_recipe(&self, item: &str) -> Option<usize> {
+        self.preferred.get(item).copied()
+    }
+
+    /// Pins the recipe at `index` in [`self.recipes_for(item)`] as the one to use for `item`,
+    /// regardless of the current strategy. Fails without changing anything if there is no such
+    /// recipe.
+    ///
+    /// [`self.recipes_for(item)`]: #method.recipes_for
+    pub fn prefer_recipe(&mut self, item: &str, index: usize) -> Result<(), PlanError> {
+        if index >= self.recipes.get(item).map_or(0, Vec::len) {
+            return Err(PlanError::NoSuchRecipe {
+                item: item.to_string(),
+                index,
+            });
+        }
+        self.preferred.insert(item.to_string(), index);
+        self.invalidate_recipes();
+        Ok(())
+    }
+
+    /// Removes the pinned recipe for `item` so that the current strategy is used instead.
+    pub fn clear_preference(&mut self, item: &str) {
+        if self.preferred.remove(item).is_some() {
+            self.invalidate_recipes();
+        }
+    }
+
+    /// Gets whether `item` is always treated as a raw material, even if it has recipes.
+    pub fn is_always_raw(&self, item: &str) -> bool {
+        self.raw_items.contains(item)
+    }
+
+    /// Gets the items that are always treated as raw materials, sorted by name.
+    pub fn always_raw(&self) -> impl Iterator<Item = &str> + '_ {
+        self.raw_items.iter().map(String::as_str)
+    }
+
+    /// Sets whether `item` is always treated as a raw material, like when it is farmed or bought
+    /// instead of crafted. Its recipes are kept, but they aren't used while this is set.
+    pub fn set_always_raw(&mut self, item: &str, raw: bool) {
+        let changed = if raw {
+            self.raw_items.insert(item.to_string())
+        } else {
+            self.raw_items.remove(item)
+        };
+        if changed {
+            self.invalidate_recipes();
+        }
+    }
+
+    /// Gets the crafting methods that recipes may use, sorted by name, or `None` if every method is
+    /// available.
+    pub fn available_methods(&self) -> Option<impl Iterator<Item = &str> + '_> {
+        self.methods
+            .as_ref()
+            .map(|methods| methods.iter().map(String::as_str))
+    }
+
+    /// Gets whether recipes which use `method` may be used.
+    pub fn is_method_available(&self, method: &str) -> bool {
+        match &self.methods {
+            Some(methods) => methods.contains(method),
+            None => true,
+        }
+    }
+
+    /// Only allows recipes which use one of `methods`, like when some machines haven't been built
+    /// yet. Items which can't be made with any of the allowed recipes are treated as raw
+    /// materials and reported by [`self.uncraftable()`].
+    ///
+    /// [`self.uncraftable()`]: #method.uncraftable
+    pub fn set_available_methods(&mut self, methods: impl IntoIterator<Item = impl Into<String>>) {
+        self.methods = Some(methods.into_iter().map(Into::into).collect());
+        self.invalidate_recipes();
+    }
+
+    /// Allows recipes which use any method again.
+    pub fn allow_all_methods(&mut self) {
+        if self.methods.take().is_some() {
+            self.invalidate_recipes();
+        }
+    }
+
+    /// Decides which recipes may be used based on the items that are always raw and the
+    /// available methods.
+    fn recipe_filter(&self) -> RecipeFilter<'_> {
+        RecipeFilter {
+            raw_items: &self.raw_items,
+            methods: self.methods.as_ref(),
+        }
+    }
+
+    /// Gets the cost of gathering a single `item` as a raw material, which is used by
+    /// [`Objective::WeightedRawMaterials`]. Raw materials cost 1 unless set otherwise.
+    pub fn raw_cost(&self, item: &str) -> f64 {
+        self.raw_costs.get(item).copied().unwrap_or(1.0)
+    }
+
+    /// Sets the cost of gathering a single `item` as a raw material, which is used by
+    /// [`Objective::WeightedRawMaterials`].
+    pub fn set_raw_cost(&mut self, item: &str, cost: f64) {
+        self.raw_costs.insert(item.to_string(), cost);
+        if self.strategy == RecipeStrategy::Optimal(Objective::WeightedRawMaterials) {
+            self.invalidate_recipes();
+        }
+    }
+
+    /// Gets what the calculator does when the recipes it would use form a cycle.
+    pub fn cycle_policy(&self) -> CyclePolicy {
+        self.cycle_policy
+    }
+
+    /// Sets what the calculator does when the recipes it would use form a cycle.
+    pub fn set_cycle_policy(&mut self, policy: CyclePolicy) {
+        self.cycle_policy = policy;
+        self.invalidate_plan();
+    }
+
+    /// Gets the cycles that were found while calculating the steps. Each cycle lists the items
+    /// involved, starting with the item furthest up the recipe tree.
+    pub fn cycles(&self) -> impl Iterator<Item = &[String]> + '_ {
+        self.resolution().cycles.iter().map(Vec::as_slice)
+    }
+
+    /// Gets the number of machines that are expected to run each step in parallel.
+    pub fn parallel_machines(&self) -> Count {
+        self.parallel_machines
+    }
+
+    /// Sets the number of machines that are expected to run each step in parallel. Each machine
+    /// needs its own copy of a recipe's catalysts, so steps that are repeated at least `machines`
+    /// times need `machines` times as many catalysts. A value of 0 is treated as 1.
+    pub fn set_parallel_machines(&mut self, machines: Count) {
+        self.parallel_machines = machines.max(1);
+        self.invalidate_plan();
+    }
+
+    /// Gets how the calculator plans for outputs that are only produced some of the time.
+    pub fn chance_mode(&self) -> ChanceMode {
+        self.chance_mode
     }
 
-    /// Gets the calculator's current target.
-    pub fn target(&self) -> &Stack {
-        &self.target
+    /// Sets how the calculator plans for outputs that are only produced some of the time.
+    pub fn set_chance_mode(&mut self, mode: ChanceMode) {
+        self.chance_mode = mode;
+        self.invalidate_plan();
+    }
+
+    /// Gets the total number of each raw material that needs to be gathered for the current plan.
+    pub fn raw_materials(&self) -> impl Iterator<Item = Stack> + '_ {
+        self.plan().into_iter().flat_map(Plan::raw_materials)
+    }
+
+    /// Gets the crafted items that will be left over once every step of the current plan has been
+    /// taken, including byproducts that weren't needed.
+    pub fn leftovers(&self) -> impl Iterator<Item = Stack> + '_ {
+        self.plan().into_iter().flat_map(Plan::leftovers)
+    }
+
+    /// Gets the items in the current plan which have recipes, but can't be made with the available
+    /// methods, so they need to be gathered as raw materials instead.
+    pub fn uncraftable(&self) -> impl Iterator<Item = &str> + '_ {
+        self.plan().into_iter().flat_map(Plan::uncraftable)
+    }
+
+    /// Gets the catalysts that are needed for the current plan. Since catalysts aren't used up, each
+    /// is only needed as many times as the single step that needs the most of it.
+    pub fn catalysts(&self) -> impl Iterator<Item = Stack> + '_ {
+        self.plan().into_iter().flat_map(Plan::catalysts)
+    }
+
+    /// Gets the durability that will be left on the partially used tools at the end of the
+    /// current plan. Tools that have been completely used up aren't included.
+    pub fn worn_tools(&self) -> impl Iterator<Item = (&str, Count)> + '_ {
+        self.plan().into_iter().flat_map(Plan::worn_tools)
+    }
+
+    /// Gets the calculator's current targets, which are planned for together.
+    pub fn targets(&self) -> &[Stack] {
+        &self.targets
     }
 
     /// Adds the given stack to the set of resources that are already available and do not need to
@@ -60,10 +627,168 @@ impl Calculator {
                     .insert(resource.item().to_string(), resource.count());
             }
         }
-        self.calculate_steps();
+        self.invalidate_resources();
+    }
+
+    /// Sets the available amount of [`resource.item()`] to exactly [`resource.count()`]. A count
+    /// of 0 removes the resource.
+    ///
+    /// [`resource.item()`]: /struct.Stack.html#method.item
+    /// [`resource.count()`]: /struct.Stack.html#method.count
+    pub fn set_resource(&mut self, resource: Stack) {
+        if resource.count() == 0 {
+            self.initial_materials.remove(resource.item());
+        } else {
+            self.initial_materials
+                .insert(resource.item().to_string(), resource.count());
+        }
+        self.invalidate_resources();
+    }
+
+    /// Takes up to [`resource.count()`] of [`resource.item()`] out of the available resources,
+    /// returning how many were actually taken. The resource is removed once none of it is left.
+    ///
+    /// [`resource.item()`]: /struct.Stack.html#method.item
+    /// [`resource.count()`]: /struct.Stack.html#method.count
+    pub fn take_resource(&mut self, resource: Stack) -> Count {
+        let Some(available) = self.initial_materials.get_mut(resource.item()) else {
+            return 0;
+        };
+        let taken = (*available).min(resource.count());
+        *available -= taken;
+        if *available == 0 {
+            self.initial_materials.remove(resource.item());
+        }
+        if taken > 0 {
+            self.invalidate_resources();
+        }
+        taken
+    }
+
+    /// Removes `item` from the available resources, including a partially used `item`. Returns the
+    /// removed resource if there was one, along with the durability that was left on the partially
+    /// used `item` if there was one.
+    pub fn remove_resource(&mut self, item: &str) -> (Option<Stack>, Option<Count>) {
+        let worn = self.worn_tools.remove(item);
+        let removed = self
+            .initial_materials
+            .remove_entry(item)
+            .map(|(item, count)| Stack::new(item, count));
+        if removed.is_some() || worn.is_some() {
+            self.invalidate_resources();
+        }
+        (removed, worn)
+    }
+
+    /// Removes all of the available resources.
+    pub fn clear_resources(&mut self) {
+        self.initial_materials.clear();
+        self.worn_tools.clear();
+        self.invalidate_resources();
+    }
+
+    /// Updates the available resources as if every step of the current plan had been taken. The
+    /// withdrawals from storage are taken out, while the targets, the leftovers and the catalysts
+    /// are added. Tools that are only partially used are kept as [`self.partly_used_tools()`].
+    /// The targets are then cleared so that the next targets are planned against the resulting
+    /// resources.
+    ///
+    /// [`self.partly_used_tools()`]: #method.partly_used_tools
+    pub fn commit_plan(&mut self) -> Result<(), PlanError> {
+        let plan = self.plan()?.clone();
+        for withdrawal in plan.withdrawals() {
+            if let Some(available) = self.initial_materials.get_mut(withdrawal.item()) {
+                *available = available.saturating_sub(withdrawal.count());
+            }
+        }
+        let targets = mem::take(&mut self.targets);
+        for stack in targets
+            .into_iter()
+            .chain(plan.leftovers())
+            .chain(plan.catalysts())
+        {
+            *self
+                .initial_materials
+                .entry(stack.item().to_string())
+                .or_default() += stack.count();
+        }
+        self.initial_materials.retain(|_, count| *count > 0);
+        self.worn_tools = plan
+            .worn_tools()
+            .map(|(tool, left)| (tool.to_string(), left))
+            .collect();
+        self.invalidate_recipes();
+        Ok(())
     }
 
-    /// Gets an iterator over all the resources that have been added with [`self.add_resource()`].
+    /// Updates the available resources after executing `recipe` `repeats` times, usually as one
+    /// of the steps from [`self.steps()`]. Gathering a raw material adds it to the resources and
+    /// taking an item from storage doesn't change anything. Otherwise the ingredients are taken
+    /// out and the outputs are added, counting outputs that are only produced some of the time
+    /// according to [`self.chance_mode()`]. New tools are taken out when they are first used and
+    /// their remaining durability is kept as [`self.partly_used_tools()`].
+    ///
+    /// [`self.steps()`]: #method.steps
+    /// [`self.chance_mode()`]: #method.chance_mode
+    /// [`self.partly_used_tools()`]: #method.partly_used_tools
+    pub fn apply_step(&mut self, recipe: &Recipe, repeats: Count) {
+        match recipe.method() {
+            "Raw Material" => {
+                let gathered = recipe.result().count() * repeats;
+                *self
+                    .initial_materials
+                    .entry(recipe.result().item().to_string())
+                    .or_default() += gathered;
+            }
+            "In Storage" => return,
+            _ => {
+                let mut used = recipe
+                    .ingredients()
+                    .iter()
+                    .map(|ingredient| (ingredient.item(), ingredient.count() * repeats))
+                    .collect::<Vec<_>>();
+                for tool in recipe.tools() {
+                    // Partially used tools are used up before new ones are taken out, the same
+                    // way as when planning.
+                    let required = tool.uses() * repeats;
+                    let left = self.worn_tools.entry(tool.item().to_string()).or_default();
+                    if required > *left {
+                        let durability = tool.durability();
+                        let new_tools = (required - *left).div_ceil(durability);
+                        used.push((tool.item(), new_tools));
+                        *left += new_tools * durability;
+                    }
+                    *left -= required;
+                }
+                self.worn_tools.retain(|_, left| *left > 0);
+                for (item, count) in used {
+                    if let Some(available) = self.initial_materials.get_mut(item) {
+                        *available = available.saturating_sub(count);
+                    }
+                }
+                for output in recipe.outputs() {
+                    *self
+                        .initial_materials
+                        .entry(output.item().to_string())
+                        .or_default() += recipe.produced(output.item(), repeats, self.chance_mode);
+                }
+                self.initial_materials.retain(|_, count| *count > 0);
+            }
+        }
+        self.invalidate_resources();
+    }
+
+    /// Throws away whatever depends on the available resources.
+    fn invalidate_resources(&mut self) {
+        // Only the optimizer looks at the available resources when choosing recipes.
+        match self.strategy {
+            RecipeStrategy::Optimal(_) => self.invalidate_recipes(),
+            _ => self.invalidate_plan(),
+        }
+    }
+
+    /// Gets an iterator over all the resources that are available, as added with
+    /// [`self.add_resource()`] and the other resource methods, sorted by item.
     ///
     /// [`self.add_resource()`]: #method.add_resource
     pub fn resources(&self) -> impl Iterator<Item = Stack> + '_ {
@@ -72,51 +797,398 @@ impl Calculator {
             .map(|(name, &count)| Stack::new(name, count))
     }
 
-    fn calculate_steps(&mut self) {
-        self.steps.clear();
-        self.materials.clone_from(&self.initial_materials);
-        self.crafted_materials.clear();
+    /// Gets the durability left on the partially used tools among the available resources. These
+    /// tools are used up before any new ones and aren't counted by [`self.resources()`].
+    ///
+    /// [`self.resources()`]: #method.resources
+    pub fn partly_used_tools(&self) -> impl Iterator<Item = (&str, Count)> + '_ {
+        self.worn_tools
+            .iter()
+            .map(|(tool, &left)| (tool.as_str(), left))
+    }
+
+    /// Makes a chooser that picks recipes for producing `targets` from `inventory` according to
+    /// `strategy`.
+    fn chooser(
+        &self,
+        targets: &[(&str, f64)],
+        inventory: &BTreeMap<String, Count>,
+        strategy: RecipeStrategy,
+    ) -> RecipeChooser<'_> {
+        let chooser = RecipeChooser::new(
+            &self.recipes,
+            &self.preferred,
+            self.recipe_filter(),
+            strategy,
+        );
+        match strategy {
+            RecipeStrategy::Optimal(objective) => {
+                let optimizer = Optimizer {
+                    recipes: &self.recipes,
+                    inventory,
+                    raw_costs: &self.raw_costs,
+                    filter: self.recipe_filter(),
+                    objective,
+                };
+                match optimizer.recipe_usage(targets) {
+                    Some(usage) => chooser.with_usage(usage),
+                    None => chooser,
+                }
+            }
+            _ => chooser,
+        }
+    }
+
+    /// Gets the recipes chosen for the current targets, choosing them if that hasn't been done
+    /// since something they depend on changed.
+    fn resolution(&self) -> &Resolution {
+        self.resolution.get_or_init(|| {
+            self.choose_recipes(&self.targets, &self.initial_materials, self.strategy)
+        })
+    }
+
+    /// Chooses the recipes for producing `targets` from `inventory` according to `strategy`.
+    fn choose_recipes(
+        &self,
+        targets: &[Stack],
+        inventory: &BTreeMap<String, Count>,
+        strategy: RecipeStrategy,
+    ) -> Resolution {
+        let amounts = targets
+            .iter()
+            .map(|target| (target.item(), target.count() as f64))
+            .collect::<Vec<_>>();
+        self.chooser(&amounts, inventory, strategy)
+            .resolve(targets.iter().map(Stack::item))
+    }
+
+    /// Gets the plan for reaching [`self.targets()`] from the available resources. The plan is
+    /// only recalculated when something it depends on has changed since it was last requested.
+    ///
+    /// [`self.targets()`]: #method.targets
+    pub fn plan(&self) -> Result<&Plan, PlanError> {
+        self.plan
+            .get_or_init(|| {
+                self.calculate_plan(&self.targets, &self.initial_materials, self.resolution())
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Plans for reaching `targets` without changing the calculator. Each of `overrides` replaces
+    /// the available amount of its item for this plan only, so a count of 0 plans as if the item
+    /// wasn't available at all.
+    pub fn plan_for(&self, targets: &[Stack], overrides: &[Stack]) -> Result<Plan, PlanError> {
+        if overrides.is_empty() && targets == self.targets.as_slice() {
+            return self.plan().cloned();
+        }
+        let mut inventory = self.initial_materials.clone();
+        for resource in overrides {
+            inventory.insert(resource.item().to_string(), resource.count());
+        }
+        let reuse_recipes = targets == self.targets.as_slice()
+            && !matches!(self.strategy, RecipeStrategy::Optimal(_));
+        if reuse_recipes {
+            self.calculate_plan(targets, &inventory, self.resolution())
+        } else {
+            let resolution = self.choose_recipes(targets, &inventory, self.strategy);
+            self.calculate_plan(targets, &inventory, &resolution)
+        }
+    }
+
+    /// Finds the most of `item` that can be made from the available resources alone, using the
+    /// known recipes, and the resources that run out first. Recipes are chosen to gather as little
+    /// as possible rather than according to [`self.strategy()`], so recipes that can be made from
+    /// the resources are used even when the strategy would pick a different one.
+    ///
+    /// [`self.strategy()`]: #method.strategy
+    pub fn max_craftable(&self, item: &str) -> Result<CraftLimit, PlanError> {
+        let plan_count = |count| -> Result<Plan, PlanError> {
+            let targets = [Stack::new(item, count)];
+            let resolution = self.choose_recipes(
+                &targets,
+                &self.initial_materials,
+                RecipeStrategy::Optimal(Objective::RawMaterials),
+            );
+            self.calculate_plan(&targets, &self.initial_materials, &resolution)
+        };
+        // Returns what would need to be gathered to make `count` of the item.
+        let shortfall = |count| -> Result<Vec<Stack>, PlanError> {
+            Ok(plan_count(count)?.raw_materials().collect())
+        };
+        let first = plan_count(1)?;
+        let mut limited_by = first.raw_materials().collect::<Vec<_>>();
+        if !limited_by.is_empty() {
+            return Ok(CraftLimit {
+                count: Some(0),
+                limited_by,
+            });
+        }
+        // Catalysts are given back and partially used tools only wear down, so if neither
+        // anything else is taken from storage nor any tools wear down, more can always be made.
+        let uses_up_resources = first.withdrawals().any(|withdrawal| {
+            withdrawal.count() > first.catalysts.get(withdrawal.item()).copied().unwrap_or(0)
+        }) || self
+            .worn_tools
+            .iter()
+            .any(|(tool, &left)| first.tool_durability.get(tool).copied().unwrap_or(0) < left);
+        if !uses_up_resources {
+            return Ok(CraftLimit {
+                count: None,
+                limited_by,
+            });
+        }
+        // The linear program estimates the most that can be made, which is usually close, so the
+        // search starts there and moves away from it in growing steps until it finds a count that
+        // can be made and a count that can't. The counts in between are then narrowed down.
+        let optimizer = Optimizer {
+            recipes: &self.recipes,
+            inventory: &self.initial_materials,
+            raw_costs: &self.raw_costs,
+            filter: self.recipe_filter(),
+            objective: Objective::RawMaterials,
+        };
+        let mut count = optimizer.max_output(item).map_or(1, |estimate| {
+            (estimate.floor() as Count).clamp(1, MAX_CRAFTABLE_SEARCH)
+        });
+        let mut low = 1;
+        let mut high = None;
+        let mut step = 1;
+        while count > low {
+            let missing = shortfall(count)?;
+            if missing.is_empty() {
+                low = count;
+                break;
+            }
+            high = Some(count);
+            limited_by = missing;
+            count = count.saturating_sub(step).max(low);
+            step = step.saturating_mul(2);
+        }
+        let mut step = 1;
+        while high.is_none() && low < MAX_CRAFTABLE_SEARCH {
+            let count = low.saturating_add(step).min(MAX_CRAFTABLE_SEARCH);
+            let missing = shortfall(count)?;
+            if missing.is_empty() {
+                low = count;
+            } else {
+                high = Some(count);
+                limited_by = missing;
+            }
+            step = step.saturating_mul(2);
+        }
+        let Some(mut high) = high else {
+            return Ok(CraftLimit {
+                count: Some(low),
+                limited_by,
+            });
+        };
+        while high - low > 1 {
+            let count = low + (high - low) / 2;
+            let missing = shortfall(count)?;
+            if missing.is_empty() {
+                low = count;
+            } else {
+                high = count;
+                limited_by = missing;
+            }
+        }
+        Ok(CraftLimit {
+            count: Some(low),
+            limited_by,
+        })
+    }
+
+    /// Finds every item that can be crafted from the available resources alone, along with the
+    /// most of each that can be made. Items are reachable when one of their usable recipes only
+    /// needs available or reachable items, and they are listed by their
+    /// [depth](Craftable::depth()), then by name.
+    pub fn craftable_items(&self) -> Result<Vec<Craftable>, PlanError> {
+        let filter = self.recipe_filter();
+        let mut depths = self
+            .initial_materials
+            .iter()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(item, _)| (item.as_str(), 0))
+            .collect::<HashMap<_, _>>();
+        let mut frontier = depths.keys().copied().collect::<Vec<_>>();
+        let mut depth = 0;
+        // Each round only finds the items whose recipes need something found in the last round,
+        // so every item is found at the depth of its shortest chain.
+        while !frontier.is_empty() {
+            depth += 1;
+            let mut found = vec![];
+            for item in frontier {
+                for recipe in self.uses_of(item) {
+                    let output = recipe.result().item();
+                    if !depths.contains_key(output)
+                        && filter.allows(output, recipe)
+                        && recipe.requirements().all(|item| depths.contains_key(item))
+                    {
+                        found.push(output);
+                    }
+                }
+            }
+            found.sort_unstable();
+            found.dedup();
+            for &item in &found {
+                depths.insert(item, depth);
+            }
+            frontier = found;
+        }
+        let mut craftable = depths
+            .into_iter()
+            .filter(|&(_, depth)| depth > 0)
+            .collect::<Vec<_>>();
+        craftable.sort_unstable_by(|&(a, a_depth), &(b, b_depth)| {
+            a_depth.cmp(&b_depth).then_with(|| a.cmp(b))
+        });
+        let mut items = vec![];
+        for (item, depth) in craftable {
+            let limit = self.max_craftable(item)?;
+            if limit.count() != Some(0) {
+                items.push(Craftable {
+                    limit,
+                    item: item.to_string(),
+                    depth,
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    /// Throws away the current plan so that it is recalculated the next time it is needed.
+    fn invalidate_plan(&mut self) {
+        self.plan.take();
+    }
+
+    /// Throws away the chosen recipes and the current plan so that both are recalculated the next
+    /// time they are needed.
+    fn invalidate_recipes(&mut self) {
+        self.resolution.take();
+        self.plan.take();
+    }
+
+    /// Plans the steps for reaching `targets` from `inventory` with the recipes in `resolution`.
+    fn calculate_plan(
+        &self,
+        targets: &[Stack],
+        inventory: &BTreeMap<String, Count>,
+        resolution: &Resolution,
+    ) -> Result<Plan, PlanError> {
+        if let Some(target) = targets.iter().find(|target| target.count() == 0) {
+            return Err(PlanError::ZeroCount(target.item().to_string()));
+        }
+        if let (Some(cycle), CyclePolicy::Fail) = (resolution.cycles.first(), self.cycle_policy) {
+            return Err(PlanError::Cycle(cycle.clone()));
+        }
+        let mut steps = vec![];
+        let mut materials = inventory.clone();
+        let mut crafted_materials = BTreeMap::<String, Count>::new();
+        let mut raw_materials = BTreeMap::<String, Count>::new();
+        let mut withdrawals = BTreeMap::<String, Count>::new();
+        let mut uncraftable = BTreeSet::new();
+        let mut catalysts = BTreeMap::<String, Count>::new();
+        let mut tool_durability = self.worn_tools.clone();
         let mut to_craft = HashMap::new();
-        to_craft.insert(self.target.item(), self.target.count());
-        let mut craft_order = DoublePriorityQueue::new();
-        craft_order.push(self.target.item(), 0);
+        let mut craft_order = DoublePriorityQueue::<_, usize>::new();
+        for target in targets {
+            *to_craft.entry(target.item()).or_default() += target.count();
+            craft_order.push(target.item(), 0);
+        }
+        let mut deferred = HashSet::new();
         while let Some((next_craft, _)) = craft_order.pop_min() {
+            // Items that are a byproduct of another item that still has to be crafted are
+            // crafted afterwards, so that the byproducts can be used. Each item is only put off
+            // once so that recipes which produce each other as byproducts can't loop forever.
+            let is_pending_byproduct = to_craft.keys().any(|&item| {
+                item != next_craft
+                    && resolution.recipes.get(item).is_some_and(|recipe| {
+                        recipe
+                            .byproducts()
+                            .iter()
+                            .any(|byproduct| byproduct.item() == next_craft)
+                    })
+            });
+            if to_craft.contains_key(next_craft)
+                && is_pending_byproduct
+                && deferred.insert(next_craft)
+            {
+                let last_priority = craft_order.peek_max().map_or(0, |(_, &priority)| priority);
+                craft_order.push(next_craft, last_priority.saturating_add(1));
+                continue;
+            }
             if let Some(mut count) = to_craft.remove(next_craft) {
-                if let Some(available) = self.crafted_materials.get_mut(next_craft) {
+                if let Some(available) = crafted_materials.get_mut(next_craft) {
                     let retrieved = (*available).min(count);
                     *available -= retrieved;
                     count -= retrieved;
                 }
-                if let Some(available) = self.materials.get_mut(next_craft) {
+                if let Some(available) = materials.get_mut(next_craft) {
                     let retrieved = (*available).min(count);
                     if retrieved > 0 {
-                        self.steps.push((
-                            Rc::new(Recipe::new(
+                        steps.push((
+                            Arc::new(Recipe::new(
                                 Stack::new(next_craft, 1),
                                 "In Storage",
                                 vec![Stack::new(next_craft, 1)],
                             )),
                             retrieved,
                         ));
+                        *withdrawals.entry(next_craft.to_string()).or_default() += retrieved;
                         *available -= retrieved;
                         count -= retrieved;
                     }
                 }
                 if count > 0 {
-                    if let Some(recipe) = self.recipes.get(next_craft) {
-                        let per_execution = recipe.result().count();
-                        let repeats = (1..).find(|i| i * per_execution >= count).unwrap();
-                        self.steps.push((Rc::clone(recipe), repeats));
-                        let produced = per_execution * repeats;
+                    if let Some(recipe) = resolution.recipes.get(next_craft) {
+                        let repeats = self.chance_mode.repeats_for(
+                            count,
+                            recipe.result().count(),
+                            recipe.chance(next_craft),
+                        );
+                        steps.push((Arc::clone(recipe), repeats));
+                        let produced = recipe.produced(next_craft, repeats, self.chance_mode);
                         if produced > count {
                             let excess = produced - count;
-                            // We don't need to worry about overwriting an existing entry because
-                            // that would require `*available > count` up above, which always makes
-                            // `retrieved == count`.
-                            self.crafted_materials
-                                .insert(next_craft.to_string(), excess);
+                            *crafted_materials.entry(next_craft.to_string()).or_default() += excess;
+                        }
+                        for byproduct in recipe.byproducts() {
+                            *crafted_materials
+                                .entry(byproduct.item().to_string())
+                                .or_default() += self.chance_mode.produced(
+                                byproduct.count(),
+                                recipe.chance(byproduct.item()),
+                                repeats,
+                            );
                         }
-                        for ingredient in recipe.ingredients() {
+                        let mut needs = recipe
+                            .ingredients()
+                            .iter()
+                            .map(|ingredient| (ingredient.item(), ingredient.count() * repeats))
+                            .collect::<Vec<_>>();
+                        for catalyst in recipe.catalysts() {
+                            let required = catalyst.count() * repeats.min(self.parallel_machines);
+                            let known = catalysts.entry(catalyst.item().to_string()).or_default();
+                            if required > *known {
+                                needs.push((catalyst.item(), required - *known));
+                                *known = required;
+                            }
+                        }
+                        for tool in recipe.tools() {
+                            // Partially used tools are passed on from earlier steps, so new tools
+                            // are only needed once those have been used up.
+                            let required = tool.uses() * repeats;
+                            let left = tool_durability.entry(tool.item().to_string()).or_default();
+                            if required > *left {
+                                let durability = tool.durability();
+                                let new_tools = (required - *left).div_ceil(durability);
+                                needs.push((tool.item(), new_tools));
+                                *left += new_tools * durability;
+                            }
+                            *left -= required;
+                        }
+                        for (item, count) in needs {
                             let next_priority = craft_order
                                 .peek_max()
                                 .map(|(_, &priority)| priority + 1)
@@ -130,32 +1202,42 @@ impl Calculator {
                                     to_craft.into_iter().enumerate().map(|(idx, c)| (c, idx)),
                                 );
                             }
-                            craft_order.push_increase(ingredient.item(), next_priority);
-                            *to_craft.entry(ingredient.item()).or_default() +=
-                                ingredient.count() * repeats;
+                            craft_order.push_increase(item, next_priority);
+                            *to_craft.entry(item).or_default() += count;
                         }
                     } else {
-                        self.steps.push((
-                            Rc::new(Recipe::new(
+                        steps.push((
+                            Arc::new(Recipe::new(
                                 Stack::new(next_craft, 1),
                                 "Raw Material",
                                 vec![Stack::new(next_craft, 1)],
                             )),
                             count,
                         ));
+                        *raw_materials.entry(next_craft.to_string()).or_default() += count;
+                        let recipes = self.recipes.get(next_craft).map_or(&[][..], Vec::as_slice);
+                        if !recipes.is_empty()
+                            && !self.raw_items.contains(next_craft)
+                            && recipes
+                                .iter()
+                                .all(|recipe| !self.is_method_available(recipe.method()))
+                        {
+                            uncraftable.insert(next_craft.to_string());
+                        }
                     }
                 }
             }
         }
         debug_assert!(to_craft.is_empty());
         let mut checked_steps = vec![];
+        let mut stage_starts = vec![0];
         let mut available_materials = HashSet::new();
         let mut from_storage = HashMap::new();
-        let mut steps_to_check = mem::take(&mut self.steps);
+        let mut steps_to_check = steps;
         let mut tmp = vec![];
         // Separate out the raw materials
         {
-            let mut raw_materials = HashMap::new();
+            let mut raw_materials = BTreeMap::new();
             for (step, repeats) in steps_to_check.drain(..) {
                 if step.method() != "Raw Material" {
                     tmp.push((step, repeats));
@@ -194,25 +1276,35 @@ impl Calculator {
         // Build out the set of steps that can be taken using only the raw materials and the
         // things that have already been crafted.
         while !steps_to_check.is_empty() {
-            let mut current_stage = HashMap::new();
+            let mut current_stage = BTreeMap::new();
             for (step, repeats) in steps_to_check.drain(..) {
-                if !step.ingredients().iter().all(|stack| {
-                    available_materials.contains(stack.item())
+                let needs = step
+                    .ingredients()
+                    .iter()
+                    .map(|stack| (stack.item(), stack.count() * repeats))
+                    .chain(
+                        step.catalysts()
+                            .iter()
+                            .map(|stack| (stack.item(), stack.count())),
+                    )
+                    .chain(step.tools().iter().map(|tool| (tool.item(), 1)));
+                if !needs.clone().all(|(item, needed)| {
+                    available_materials.contains(item)
                         || from_storage
-                            .get(stack.item())
+                            .get(item)
                             .filter(|&&(ref recipe, rec_repeats)| {
-                                recipe.result().count() * rec_repeats >= stack.count() * repeats
+                                recipe.result().count() * rec_repeats >= needed
                             })
                             .is_some()
                 }) {
                     tmp.push((step, repeats));
                     continue;
                 }
-                for stack in step.ingredients().iter() {
-                    match from_storage.remove_entry(stack.item()) {
+                for (item, _) in needs {
+                    match from_storage.remove_entry(item) {
                         None => {}
                         Some((item, (recipe, rec_repeats))) => {
-                            checked_steps.push((recipe, rec_repeats * repeats));
+                            checked_steps.push((recipe, rec_repeats));
                             available_materials.insert(item);
                         }
                     }
@@ -224,47 +1316,202 @@ impl Calculator {
                     }
                 }
             }
+            if current_stage.is_empty() {
+                // None of the remaining steps will ever have all of their ingredients, so keep
+                // them in the order they were found rather than waiting forever.
+                stage_starts.push(checked_steps.len());
+                checked_steps.append(&mut tmp);
+                break;
+            }
+            // Items taken from storage for this stage end the stage before it, since nothing
+            // needs to be crafted first.
+            stage_starts.push(checked_steps.len());
             checked_steps.reserve(current_stage.len());
             for (result, action) in current_stage {
+                available_materials.extend(
+                    action
+                        .0
+                        .byproducts()
+                        .iter()
+                        .map(|byproduct| byproduct.item().to_string()),
+                );
                 checked_steps.push(action);
                 available_materials.insert(result);
             }
             steps_to_check.append(&mut tmp);
         }
-        self.steps = checked_steps;
+        // The first stage is empty when there was nothing to gather or take from storage for it.
+        stage_starts.dedup();
+        stage_starts.retain(|&start| start < checked_steps.len());
+        Ok(Plan {
+            steps: checked_steps,
+            stage_starts,
+            leftovers: crafted_materials,
+            raw_materials,
+            withdrawals,
+            uncraftable,
+            catalysts,
+            tool_durability,
+            cycles: resolution.cycles.clone(),
+        })
     }
 
-    /// Sets the recipe for creating [`recipe.result()`] [`.item()`].
+    /// Sets the recipe for creating [`recipe.result()`] [`.item()`]. Any other recipes for that
+    /// item are kept as alternatives, as with [`self.add_recipes()`]. Fails without changing
+    /// anything if [`recipe.validate()`] finds a problem.
     ///
     /// [`recipe.result()`]: /struct.Recipe.html#method.result
     /// [`.item()`]: /struct.Stack.html#method.item
-    pub fn set_recipe(&mut self, recipe: Recipe) {
-        self.add_recipes(vec![recipe]);
+    /// [`self.add_recipes()`]: #method.add_recipes
+    /// [`recipe.validate()`]: /struct.Recipe.html#method.validate
+    pub fn set_recipe(&mut self, recipe: Recipe) -> Result<(), RecipeError> {
+        recipe.validate()?;
+        self.add_recipes_with_lines([(None, recipe)]);
+        Ok(())
+    }
+
+    /// Sets the only recipe for creating [`recipe.result()`] [`.item()`], replacing any other
+    /// recipes for that item. Fails without changing anything if [`recipe.validate()`] finds a
+    /// problem.
+    ///
+    /// [`recipe.result()`]: /struct.Recipe.html#method.result
+    /// [`.item()`]: /struct.Stack.html#method.item
+    /// [`recipe.validate()`]: /struct.Recipe.html#method.validate
+    pub fn replace_recipes(&mut self, recipe: Recipe) -> Result<(), RecipeError> {
+        recipe.validate()?;
+        let name = recipe.result().item().to_string();
+        self.preferred.remove(&name);
+        let recipe = Arc::new(recipe);
+        self.index_uses(&recipe);
+        for replaced in self.recipes.insert(name, vec![recipe]).unwrap_or_default() {
+            self.unindex_uses(&replaced);
+        }
+        self.invalidate_recipes();
+        Ok(())
+    }
+
+    /// Adds the specified recipes to the calculator. If multiple recipes produce the same item,
+    /// all of them are kept as alternatives and the calculator's [`strategy()`] decides which one
+    /// to use. Recipes which are identical to a known recipe are ignored, and recipes with
+    /// [`problems()`] are left out and reported.
+    ///
+    /// [`strategy()`]: #method.strategy
+    /// [`problems()`]: /struct.Recipe.html#method.problems
+    pub fn add_recipes(&mut self, recipes: Vec<Recipe>) -> Vec<Diagnostic> {
+        self.add_recipes_with_lines(recipes.into_iter().map(|recipe| (None, recipe)))
+    }
+
+    /// Parses the recipes in `s`, like the contents of a recipe file, and adds them as with
+    /// [`self.add_recipes()`]. Recipes which don't specify a method use `default_method`. Any
+    /// problems with the recipes are reported along with the line where the recipe starts.
+    /// Recipes which can't be parsed are skipped, and the position where each of them failed is
+    /// returned as well, so that the rest of the recipes are still loaded.
+    ///
+    /// [`self.add_recipes()`]: #method.add_recipes
+    pub fn load_recipes(
+        &mut self,
+        s: &str,
+        default_method: &str,
+    ) -> (Vec<Diagnostic>, Vec<ParseError>) {
+        let (recipes, errors) = Recipe::parse_all_with_lines(s, default_method);
+        let diagnostics = self.add_recipes_with_lines(
+            recipes
+                .into_iter()
+                .map(|(line, recipe)| (Some(line), recipe)),
+        );
+        (diagnostics, errors)
+    }
+
+    fn add_recipes_with_lines(
+        &mut self,
+        recipes: impl IntoIterator<Item = (Option<usize>, Recipe)>,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        for (line, recipe) in recipes {
+            let problems = recipe.problems();
+            if !problems.is_empty() {
+                diagnostics.extend(
+                    problems
+                        .into_iter()
+                        .map(|problem| Diagnostic::new(problem, line)),
+                );
+                continue;
+            }
+            let alternatives = self
+                .recipes
+                .entry(recipe.result().item().to_string())
+                .or_default();
+            if !alternatives.iter().any(|known| **known == recipe) {
+                let recipe = Arc::new(recipe);
+                alternatives.push(Arc::clone(&recipe));
+                self.index_uses(&recipe);
+            }
+        }
+        self.invalidate_recipes();
+        diagnostics
+    }
+
+    /// Sets the calculator's only target, replacing any existing targets.
+    pub fn set_target(&mut self, target: Stack) {
+        self.targets = vec![target];
+        self.invalidate_recipes();
+    }
+
+    /// Adds `target` to the calculator's targets. If the item is already a target, its count is
+    /// increased instead.
+    pub fn add_target(&mut self, target: Stack) {
+        match self
+            .targets
+            .iter_mut()
+            .find(|existing| existing.item() == target.item())
+        {
+            Some(existing) => {
+                *existing = Stack::new(target.item(), existing.count() + target.count())
+            }
+            None => self.targets.push(target),
+        }
+        self.invalidate_recipes();
+    }
+
+    /// Removes `item` from the calculator's targets, returning the removed target if there was
+    /// one.
+    pub fn remove_target(&mut self, item: &str) -> Option<Stack> {
+        let idx = self
+            .targets
+            .iter()
+            .position(|target| target.item() == item)?;
+        let removed = self.targets.remove(idx);
+        self.invalidate_recipes();
+        Some(removed)
     }
 
-    /// Sets the calculator to use the specified recipes for creating their results. If multiple
-    /// recipes produce the same item, the later recipe overrides the earlier one(s).
-    pub fn add_recipes(&mut self, recipes: Vec<Recipe>) {
-        for recipe in recipes {
-            let name = recipe.result().item();
-            self.recipes.insert(name.to_string(), Rc::new(recipe));
-        }
-        self.calculate_steps();
+    /// Removes all of the calculator's targets.
+    pub fn clear_targets(&mut self) {
+        self.targets.clear();
+        self.invalidate_recipes();
     }
 
-    /// Sets the target for the calculator.
-    pub fn set_target(&mut self, target: Stack) {
-        self.target = target;
-        self.calculate_steps();
+    /// Plans for producing each of `targets` continuously at the given number of items per minute.
+    /// Unlike [`self.steps()`], this doesn't use any of the available resources since they would
+    /// eventually run out.
+    ///
+    /// [`self.steps()`]: #method.steps
+    pub fn rates(&self, targets: &[(&str, f64)]) -> Result<RatePlan, PlanError> {
+        let resolution = self
+            .chooser(targets, &BTreeMap::new(), self.strategy)
+            .resolve(targets.iter().map(|&(item, _)| item));
+        if let (Some(cycle), CyclePolicy::Fail) = (resolution.cycles.first(), self.cycle_policy) {
+            return Err(PlanError::Cycle(cycle.clone()));
+        }
+        Ok(rates::plan(&resolution.recipes, targets))
     }
 
-    /// Gets the steps to convert the available materials into [`self.target()`].
+    /// Gets the steps to convert the available materials into [`self.targets()`], in the order
+    /// described by [`Plan::steps()`].
     ///
-    /// [`self.target()`]: #method.target
+    /// [`self.targets()`]: #method.targets
     pub fn steps(&self) -> impl Iterator<Item = (&Recipe, Count)> + '_ {
-        self.steps
-            .iter()
-            .map(|&(ref recipe, count)| (Rc::as_ref(recipe), count))
+        self.plan().into_iter().flat_map(Plan::steps)
     }
 }
 
@@ -276,7 +1523,10 @@ impl Default for Calculator {
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
     use super::*;
+    use crate::{Chance, Tool};
 
     #[test]
     fn calculate_raw_material() {
@@ -315,11 +1565,13 @@ mod tests {
             ),
         ];
         let mut calculator = Calculator::new();
-        calculator.set_recipe(Recipe::new(
-            Stack::new("Charcoal", 1),
-            "Furnace",
-            vec![Stack::new("Oak Log", 1)],
-        ));
+        calculator
+            .set_recipe(Recipe::new(
+                Stack::new("Charcoal", 1),
+                "Furnace",
+                vec![Stack::new("Oak Log", 1)],
+            ))
+            .unwrap();
         calculator.set_target(Stack::new("Charcoal", 1));
         let actual = calculator.steps().collect::<Vec<_>>();
         assert_eq!(&expected[..], &actual[..]);
@@ -346,16 +1598,41 @@ mod tests {
             ),
         ];
         let mut calculator = Calculator::new();
-        calculator.set_recipe(Recipe::new(
-            Stack::new("Oak Wood Planks", 4),
-            "Crafting Table",
-            vec![Stack::new("Oak Log", 1)],
-        ));
+        calculator
+            .set_recipe(Recipe::new(
+                Stack::new("Oak Wood Planks", 4),
+                "Crafting Table",
+                vec![Stack::new("Oak Log", 1)],
+            ))
+            .unwrap();
         calculator.set_target(Stack::new("Oak Wood Planks", 1));
         let actual = calculator.steps().collect::<Vec<_>>();
         assert_eq!(&expected[..], &actual[..]);
     }
 
+    #[test]
+    fn calculate_raw_totals_and_leftovers() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_target(Stack::new("Stick", 2));
+        calculator.add_target(Stack::new("Wooden Pressure Plate", 1));
+        calculator
+            .set_recipe(Recipe::new(
+                Stack::new("Wooden Pressure Plate", 1),
+                "Crafting Table",
+                vec![Stack::new("Oak Wood Planks", 2)],
+            ))
+            .unwrap();
+        assert_eq!(
+            vec![Stack::new("Oak Log", 1)],
+            calculator.raw_materials().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Stack::new("Stick", 2)],
+            calculator.leftovers().collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn calculate_leftover_reuse() {
         let expected = [
@@ -500,4 +1777,1000 @@ mod tests {
         let actual = calculator.steps().collect::<Vec<_>>();
         assert_eq!(&expected[..], &actual[..]);
     }
+
+    fn stick_recipes() -> Vec<Recipe> {
+        vec![
+            Recipe::new(
+                Stack::new("Stick", 4),
+                "Crafting Table",
+                vec![Stack::new("Oak Wood Planks", 2)],
+            ),
+            Recipe::new(
+                Stack::new("Stick", 1),
+                "Crafting Table",
+                vec![Stack::new("Bamboo", 2)],
+            ),
+            Recipe::new(
+                Stack::new("Oak Wood Planks", 4),
+                "Crafting Table",
+                vec![Stack::new("Oak Log", 1)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn manage_resources() {
+        let mut calculator = Calculator::new();
+        calculator.add_resource(Stack::new("Oak Log", 4));
+        calculator.set_resource(Stack::new("Stick", 2));
+        calculator.set_resource(Stack::new("Oak Log", 3));
+        assert_eq!(2, calculator.take_resource(Stack::new("Oak Log", 2)));
+        assert_eq!(1, calculator.take_resource(Stack::new("Oak Log", 2)));
+        assert_eq!(0, calculator.take_resource(Stack::new("Oak Log", 2)));
+        assert_eq!(
+            vec![Stack::new("Stick", 2)],
+            calculator.resources().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            (Some(Stack::new("Stick", 2)), None),
+            calculator.remove_resource("Stick")
+        );
+        calculator.add_resource(Stack::new("Oak Log", 1));
+        calculator.clear_resources();
+        assert_eq!(0, calculator.resources().count());
+    }
+
+    #[test]
+    fn commit_plan_to_resources() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_resource(Stack::new("Oak Wood Planks", 1));
+        calculator.add_resource(Stack::new("Bamboo", 3));
+        calculator.set_target(Stack::new("Stick", 2));
+        assert_eq!(Ok(()), calculator.commit_plan());
+        assert!(calculator.targets().is_empty());
+        assert_eq!(
+            vec![
+                Stack::new("Bamboo", 3),
+                Stack::new("Oak Wood Planks", 3),
+                Stack::new("Stick", 4),
+            ],
+            calculator.resources().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn commit_plan_with_worn_tools() {
+        let butcher = Recipe::new(
+            Stack::new("Raw Beef", 1),
+            "Butchering",
+            vec![Stack::new("Cow Carcass", 1)],
+        )
+        .with_tools(vec![Tool::new("Knife", 1, 5)]);
+        let mut calculator = Calculator::new();
+        calculator.set_recipe(butcher).unwrap();
+        calculator.add_resource(Stack::new("Cow Carcass", 8));
+        calculator.add_resource(Stack::new("Knife", 1));
+        calculator.set_target(Stack::new("Raw Beef", 1));
+        assert_eq!(Ok(()), calculator.commit_plan());
+        assert_eq!(
+            vec![("Knife", 4)],
+            calculator.partly_used_tools().collect::<Vec<_>>()
+        );
+        calculator.set_target(Stack::new("Raw Beef", 5));
+        assert!(calculator
+            .steps()
+            .all(|(recipe, _)| recipe.result().item() != "Knife"));
+        assert_eq!(Ok(()), calculator.commit_plan());
+        assert_eq!(0, calculator.partly_used_tools().count());
+        assert_eq!(
+            vec![Stack::new("Cow Carcass", 3), Stack::new("Raw Beef", 5)],
+            calculator.resources().collect::<Vec<_>>()
+        );
+        calculator.add_resource(Stack::new("Knife", 1));
+        calculator.set_target(Stack::new("Raw Beef", 6));
+        assert_eq!(Ok(()), calculator.commit_plan());
+        calculator.set_target(Stack::new("Raw Beef", 7));
+        assert_eq!(0, calculator.raw_materials().count());
+        assert_eq!((None, Some(4)), calculator.remove_resource("Knife"));
+        assert_eq!(
+            vec![Stack::new("Knife", 1)],
+            calculator.raw_materials().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn apply_steps_one_at_a_time() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.set_target(Stack::new("Stick", 8));
+        let (log, repeats) = calculator
+            .steps()
+            .map(|(recipe, repeats)| (recipe.clone(), repeats))
+            .next()
+            .unwrap();
+        assert_eq!(("Raw Material", 1), (log.method(), repeats));
+        calculator.apply_step(&log, 1);
+        assert_eq!(
+            vec![Stack::new("Oak Log", 1)],
+            calculator.resources().collect::<Vec<_>>()
+        );
+        let planks = stick_recipes().remove(2);
+        calculator.apply_step(&planks, 1);
+        let sticks = stick_recipes().remove(0);
+        calculator.apply_step(&sticks, 1);
+        assert_eq!(
+            vec![Stack::new("Oak Wood Planks", 2), Stack::new("Stick", 4)],
+            calculator.resources().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some((&sticks, 1)),
+            calculator
+                .steps()
+                .find(|(recipe, _)| recipe.method() != "In Storage")
+        );
+        calculator.apply_step(&sticks, 1);
+        assert!(calculator
+            .steps()
+            .all(|(recipe, _)| recipe.method() == "In Storage"));
+    }
+
+    #[test]
+    fn apply_tool_steps_one_at_a_time() {
+        let butcher = Recipe::new(
+            Stack::new("Raw Beef", 1),
+            "Butchering",
+            vec![Stack::new("Cow Carcass", 1)],
+        )
+        .with_tools(vec![Tool::new("Knife", 1, 5)]);
+        let mut calculator = Calculator::new();
+        calculator.set_recipe(butcher.clone()).unwrap();
+        calculator.add_resource(Stack::new("Cow Carcass", 12));
+        calculator.add_resource(Stack::new("Knife", 3));
+        for _ in 0..4 {
+            calculator.apply_step(&butcher, 3);
+        }
+        assert_eq!(
+            vec![Stack::new("Raw Beef", 12)],
+            calculator.resources().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![("Knife", 3)],
+            calculator.partly_used_tools().collect::<Vec<_>>()
+        );
+        calculator.set_target(Stack::new("Raw Beef", 15));
+        calculator.add_resource(Stack::new("Cow Carcass", 3));
+        assert!(calculator
+            .steps()
+            .all(|(recipe, _)| recipe.result().item() != "Knife"));
+    }
+
+    #[test]
+    fn treat_item_as_raw() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.set_target(Stack::new("Stick", 4));
+        calculator.set_always_raw("Oak Wood Planks", true);
+        assert!(calculator.is_always_raw("Oak Wood Planks"));
+        assert_eq!(
+            vec![Stack::new("Oak Wood Planks", 2)],
+            calculator.raw_materials().collect::<Vec<_>>()
+        );
+        calculator.set_always_raw("Oak Wood Planks", false);
+        assert_eq!(
+            vec![Stack::new("Oak Log", 1)],
+            calculator.raw_materials().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn restrict_methods() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![
+            Recipe::new(
+                Stack::new("Iron Ingot", 1),
+                "Furnace",
+                vec![Stack::new("Iron Ore", 1)],
+            ),
+            Recipe::new(
+                Stack::new("Iron Bars", 16),
+                "Crafting Table",
+                vec![Stack::new("Iron Ingot", 6)],
+            ),
+        ]);
+        calculator.set_target(Stack::new("Iron Bars", 16));
+        calculator.set_available_methods(["Crafting Table"]);
+        assert_eq!(
+            vec![Stack::new("Iron Ingot", 6)],
+            calculator.raw_materials().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["Iron Ingot"],
+            calculator.uncraftable().collect::<Vec<_>>()
+        );
+        calculator.allow_all_methods();
+        assert_eq!(
+            vec![Stack::new("Iron Ore", 6)],
+            calculator.raw_materials().collect::<Vec<_>>()
+        );
+        assert_eq!(0, calculator.uncraftable().count());
+    }
+
+    #[test]
+    fn find_uses() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        let planks = &stick_recipes()[2];
+        let sticks = &stick_recipes()[0];
+        assert_eq!(
+            vec![planks],
+            calculator.uses_of("Oak Log").collect::<Vec<_>>()
+        );
+        assert_eq!(vec![planks, sticks], calculator.all_uses_of("Oak Log"));
+        calculator
+            .replace_recipes(Recipe::new(
+                Stack::new("Oak Wood Planks", 4),
+                "Crafting Table",
+                vec![Stack::new("Birch Log", 1)],
+            ))
+            .unwrap();
+        assert_eq!(0, calculator.uses_of("Oak Log").count());
+    }
+
+    #[test]
+    fn find_max_craftable() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        assert_eq!(
+            vec![Stack::new("Oak Log", 1)],
+            calculator.max_craftable("Stick").unwrap().limited_by()
+        );
+        calculator.add_resource(Stack::new("Oak Log", 3));
+        calculator.add_resource(Stack::new("Oak Wood Planks", 1));
+        calculator.add_resource(Stack::new("Stick", 1));
+        let limit = calculator.max_craftable("Stick").unwrap();
+        assert_eq!(Some(25), limit.count());
+        assert_eq!(&[Stack::new("Oak Log", 1)], limit.limited_by());
+    }
+
+    #[test]
+    fn find_max_craftable_from_many_resources() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_resource(Stack::new("Oak Log", 1_000_000));
+        let limit = calculator.max_craftable("Stick").unwrap();
+        assert_eq!(Some(8_000_000), limit.count());
+        assert_eq!(&[Stack::new("Oak Log", 1)], limit.limited_by());
+    }
+
+    #[test]
+    fn find_unlimited_craftable() {
+        let mut calculator = Calculator::new();
+        calculator
+            .set_recipe(
+                Recipe::new(Stack::new("Water", 1), "Bucket", vec![])
+                    .with_catalysts(vec![Stack::new("Water Source", 1)])
+                    .with_chance("Water", Chance::from_percent(50.0)),
+            )
+            .unwrap();
+        calculator.set_chance_mode(ChanceMode::Guaranteed(Chance::from_percent(99.0)));
+        calculator.add_resource(Stack::new("Water Source", 1));
+        let limit = calculator.max_craftable("Water").unwrap();
+        assert_eq!(None, limit.count());
+        assert!(limit.limited_by().is_empty());
+        let craftable = calculator.craftable_items().unwrap();
+        assert_eq!(None, craftable[0].count());
+    }
+
+    #[test]
+    fn find_max_craftable_with_other_recipe() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_resource(Stack::new("Bamboo", 4));
+        // The strategy alone would make sticks from planks, which need logs.
+        assert_eq!(
+            vec![Stack::new("Oak Log", 1)],
+            calculator
+                .plan_for(&[Stack::new("Stick", 1)], &[])
+                .unwrap()
+                .raw_materials()
+                .collect::<Vec<_>>()
+        );
+        let limit = calculator.max_craftable("Stick").unwrap();
+        assert_eq!(Some(2), limit.count());
+        assert_eq!(&[Stack::new("Bamboo", 2)], limit.limited_by());
+    }
+
+    #[test]
+    fn find_craftable_items() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        assert!(calculator.craftable_items().unwrap().is_empty());
+        calculator.add_resource(Stack::new("Oak Log", 1));
+        let craftable = calculator.craftable_items().unwrap();
+        assert_eq!(
+            vec![("Oak Wood Planks", Some(4), 1), ("Stick", Some(8), 2)],
+            craftable
+                .iter()
+                .map(|item| (item.item(), item.count(), item.depth()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn find_craftable_items_with_other_recipes() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_recipes(vec![Recipe::new(
+            Stack::new("Oak Wood Planks", 4),
+            "Crafting Table",
+            vec![Stack::new("Stripped Oak Log", 1)],
+        )]);
+        // The first recipes for sticks and planks need items that aren't available.
+        calculator.add_resource(Stack::new("Bamboo", 4));
+        let craftable = calculator.craftable_items().unwrap();
+        assert_eq!(
+            vec![("Stick", Some(2), 1)],
+            craftable
+                .iter()
+                .map(|item| (item.item(), item.count(), item.depth()))
+                .collect::<Vec<_>>()
+        );
+        calculator.clear_resources();
+        calculator.add_resource(Stack::new("Stripped Oak Log", 1));
+        let craftable = calculator.craftable_items().unwrap();
+        assert_eq!(
+            vec![("Oak Wood Planks", Some(4), 1), ("Stick", Some(8), 2)],
+            craftable
+                .iter()
+                .map(|item| (item.item(), item.count(), item.depth()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn keep_alternative_recipes() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_recipes(stick_recipes());
+        assert_eq!(3, calculator.recipes().count());
+        assert_eq!(2, calculator.recipes_for("Stick").count());
+    }
+
+    #[test]
+    fn set_or_replace_recipe() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        let cactus_sticks = Recipe::new(
+            Stack::new("Stick", 2),
+            "Crafting Table",
+            vec![Stack::new("Cactus", 1)],
+        );
+        calculator.set_recipe(cactus_sticks.clone()).unwrap();
+        assert_eq!(3, calculator.recipes_for("Stick").count());
+        calculator.replace_recipes(cactus_sticks.clone()).unwrap();
+        assert_eq!(
+            vec![&cactus_sticks],
+            calculator.recipes_for("Stick").collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn choose_recipe_by_strategy() {
+        let bamboo_sticks = &stick_recipes()[1];
+        let plank_sticks = &stick_recipes()[0];
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.set_target(Stack::new("Stick", 4));
+        assert!(calculator.steps().any(|(recipe, _)| recipe == plank_sticks));
+        calculator.set_strategy(RecipeStrategy::FewestSteps);
+        assert!(calculator
+            .steps()
+            .any(|(recipe, _)| recipe == bamboo_sticks));
+        calculator.set_strategy(RecipeStrategy::First);
+        assert!(calculator.steps().any(|(recipe, _)| recipe == plank_sticks));
+    }
+
+    #[test]
+    fn preferred_recipe_overrides_strategy() {
+        let bamboo_sticks = &stick_recipes()[1];
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.set_target(Stack::new("Stick", 4));
+        assert_eq!(
+            Err(PlanError::NoSuchRecipe {
+                item: "Stick".to_string(),
+                index: 2
+            }),
+            calculator.prefer_recipe("Stick", 2)
+        );
+        assert_eq!(Ok(()), calculator.prefer_recipe("Stick", 1));
+        assert_eq!(
+            Some((bamboo_sticks, 4)),
+            calculator
+                .steps()
+                .find(|(recipe, _)| recipe.result().item() == "Stick")
+        );
+        calculator.clear_preference("Stick");
+        assert!(!calculator
+            .steps()
+            .any(|(recipe, _)| recipe == bamboo_sticks));
+    }
+
+    #[test]
+    fn choose_optimal_recipes() {
+        let separate_dust = Recipe::new(
+            Stack::new("Iron Dust", 1),
+            "Macerator",
+            vec![Stack::new("Iron Ore", 3)],
+        );
+        let combined_dust = Recipe::new(
+            Stack::new("Iron Dust", 1),
+            "Ore Washer",
+            vec![Stack::new("Iron Ore", 4)],
+        )
+        .with_byproducts(vec![Stack::new("Tiny Pile of Gold Dust", 1)]);
+        let gold_dust = Recipe::new(
+            Stack::new("Tiny Pile of Gold Dust", 1),
+            "Macerator",
+            vec![Stack::new("Gold Ore", 2)],
+        );
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![
+            separate_dust.clone(),
+            combined_dust.clone(),
+            gold_dust,
+        ]);
+        calculator.add_target(Stack::new("Iron Dust", 1));
+        calculator.add_target(Stack::new("Tiny Pile of Gold Dust", 1));
+        assert!(calculator
+            .steps()
+            .any(|(recipe, _)| recipe == &separate_dust));
+        calculator.set_strategy(RecipeStrategy::Optimal(Objective::RawMaterials));
+        assert!(calculator
+            .steps()
+            .any(|(recipe, _)| recipe == &combined_dust));
+        assert!(!calculator
+            .steps()
+            .any(|(recipe, _)| recipe.method() == "Macerator"));
+        calculator.set_strategy(RecipeStrategy::Optimal(Objective::WeightedRawMaterials));
+        calculator.set_raw_cost("Gold Ore", 0.1);
+        assert!(calculator
+            .steps()
+            .any(|(recipe, _)| recipe == &separate_dust));
+    }
+
+    #[test]
+    fn charge_for_catalysts_when_optimizing() {
+        let pressed = Recipe::new(
+            Stack::new("Iron Plate", 1),
+            "Press",
+            vec![Stack::new("Iron Ingot", 1)],
+        )
+        .with_catalysts(vec![Stack::new("Plate Mold", 1)]);
+        let hammered = Recipe::new(
+            Stack::new("Iron Plate", 1),
+            "Crafting Table",
+            vec![Stack::new("Iron Ingot", 2)],
+        );
+        let mold = Recipe::new(
+            Stack::new("Plate Mold", 1),
+            "Crafting Table",
+            vec![Stack::new("Steel Ingot", 50)],
+        );
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![pressed.clone(), hammered.clone(), mold]);
+        calculator.set_strategy(RecipeStrategy::Optimal(Objective::RawMaterials));
+        calculator.set_target(Stack::new("Iron Plate", 4));
+        assert!(calculator.steps().any(|(recipe, _)| recipe == &hammered));
+        calculator.set_target(Stack::new("Iron Plate", 100));
+        assert!(calculator.steps().any(|(recipe, _)| recipe == &pressed));
+    }
+
+    #[test]
+    fn recalculate_lazily() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.set_target(Stack::new("Stick", 4));
+        assert!(calculator.plan.get().is_none());
+        assert_eq!(3, calculator.steps().count());
+        calculator.add_resource(Stack::new("Oak Wood Planks", 2));
+        assert!(calculator.resolution.get().is_some());
+        assert!(calculator.plan.get().is_none());
+        assert!(calculator
+            .steps()
+            .any(|(recipe, _)| recipe.method() == "In Storage"));
+        calculator.set_target(Stack::new("Stick", 8));
+        assert!(calculator.resolution.get().is_none());
+    }
+
+    #[test]
+    fn plan_without_changing_calculator() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_resource(Stack::new("Oak Wood Planks", 4));
+        let plan = calculator
+            .plan_for(
+                &[Stack::new("Stick", 2)],
+                &[Stack::new("Oak Wood Planks", 1)],
+            )
+            .unwrap();
+        assert_eq!(
+            vec![Stack::new("Oak Log", 1)],
+            plan.raw_materials().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Stack::new("Oak Wood Planks", 1)],
+            plan.withdrawals().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Stack::new("Oak Wood Planks", 3), Stack::new("Stick", 2)],
+            plan.leftovers().collect::<Vec<_>>()
+        );
+        assert!(calculator.targets().is_empty());
+        assert_eq!(0, calculator.steps().count());
+    }
+
+    #[test]
+    fn plan_on_many_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Calculator>();
+        assert_send_sync::<Plan>();
+
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.set_target(Stack::new("Stick", 4));
+        std::thread::scope(|scope| {
+            let handles = (1..=4)
+                .map(|logs| {
+                    let calculator = &calculator;
+                    scope.spawn(move || {
+                        calculator.plan().unwrap();
+                        calculator
+                            .plan_for(&[Stack::new("Oak Wood Planks", logs * 4)], &[])
+                            .unwrap()
+                    })
+                })
+                .collect::<Vec<_>>();
+            for (logs, handle) in (1..=4).zip(handles) {
+                assert_eq!(
+                    vec![Stack::new("Oak Log", logs)],
+                    handle.join().unwrap().raw_materials().collect::<Vec<_>>()
+                );
+            }
+        });
+        assert_eq!(3, calculator.steps().count());
+    }
+
+    #[test]
+    fn report_invalid_recipes() {
+        let recipes = concat!(
+            "Oak Wood Planks (4): Oak Log (1)\n",
+            "\n",
+            "Stick (0): Oak Wood Planks (2)\n",
+            "\n",
+            "Oak Log (1) (Raw Material):\n",
+            " Oak Log (1)\n",
+        );
+        let mut calculator = Calculator::new();
+        let (diagnostics, errors) = calculator.load_recipes(recipes, "Crafting Table");
+        assert_eq!(Vec::<ParseError>::new(), errors);
+        assert_eq!(
+            vec![
+                Diagnostic::new(
+                    RecipeError::ZeroCount {
+                        recipe: "Stick".to_string(),
+                        item: "Stick".to_string(),
+                    },
+                    Some(3)
+                ),
+                Diagnostic::new(
+                    RecipeError::ReservedMethod {
+                        recipe: "Oak Log".to_string(),
+                        method: "Raw Material".to_string(),
+                    },
+                    Some(5)
+                ),
+                Diagnostic::new(
+                    RecipeError::SelfReference {
+                        recipe: "Oak Log".to_string(),
+                    },
+                    Some(5)
+                ),
+            ],
+            diagnostics
+        );
+        assert_eq!(1, calculator.recipes().count());
+    }
+
+    #[test]
+    fn calculate_byproduct_reuse() {
+        let smelt_iron = Recipe::new(
+            Stack::new("Iron Ingot", 1),
+            "Furnace",
+            vec![Stack::new("Iron Ore", 1)],
+        )
+        .with_byproducts(vec![Stack::new("Slag", 1)]);
+        let slag_wall = Recipe::new(
+            Stack::new("Slag Wall", 1),
+            "Crafting Table",
+            vec![Stack::new("Iron Ingot", 2), Stack::new("Slag", 2)],
+        );
+        let expected = [
+            (
+                &Recipe::new(
+                    Stack::new("Iron Ore", 1),
+                    "Raw Material",
+                    vec![Stack::new("Iron Ore", 1)],
+                ),
+                2,
+            ),
+            (&smelt_iron, 2),
+            (&slag_wall, 1),
+        ];
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![smelt_iron.clone(), slag_wall.clone()]);
+        calculator.set_target(Stack::new("Slag Wall", 1));
+        let actual = calculator.steps().collect::<Vec<_>>();
+        assert_eq!(&expected[..], &actual[..]);
+    }
+
+    #[test]
+    fn calculate_byproduct_reuse_listed_first() {
+        let smelt_iron = Recipe::new(
+            Stack::new("Iron Ingot", 1),
+            "Furnace",
+            vec![Stack::new("Iron Ore", 1)],
+        )
+        .with_byproducts(vec![Stack::new("Slag", 1)]);
+        let slag_wall = Recipe::new(
+            Stack::new("Slag Wall", 1),
+            "Crafting Table",
+            vec![Stack::new("Slag", 2), Stack::new("Iron Ingot", 2)],
+        );
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![smelt_iron.clone(), slag_wall.clone()]);
+        calculator.set_target(Stack::new("Slag Wall", 1));
+        assert!(calculator
+            .steps()
+            .all(|(recipe, _)| recipe.result().item() != "Slag"));
+        assert!(calculator.steps().any(|step| step == (&smelt_iron, 2)));
+    }
+
+    fn iron_recipes() -> Vec<Recipe> {
+        vec![
+            Recipe::new(
+                Stack::new("Iron Ingot", 9),
+                "Crafting Table",
+                vec![Stack::new("Iron Block", 1)],
+            ),
+            Recipe::new(
+                Stack::new("Iron Block", 1),
+                "Crafting Table",
+                vec![Stack::new("Iron Ingot", 9)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn break_cycle_as_raw() {
+        let expected = [
+            (
+                &Recipe::new(
+                    Stack::new("Iron Ingot", 1),
+                    "Raw Material",
+                    vec![Stack::new("Iron Ingot", 1)],
+                ),
+                9,
+            ),
+            (&iron_recipes()[1], 1),
+        ];
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(iron_recipes());
+        calculator.set_target(Stack::new("Iron Block", 1));
+        let actual = calculator.steps().collect::<Vec<_>>();
+        assert_eq!(&expected[..], &actual[..]);
+        assert_eq!(
+            vec![&["Iron Block".to_string(), "Iron Ingot".to_string()][..]],
+            calculator.cycles().collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn fail_on_cycle() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(iron_recipes());
+        calculator.set_cycle_policy(CyclePolicy::Fail);
+        calculator.set_target(Stack::new("Iron Ingot", 9));
+        assert_eq!(0, calculator.steps().count());
+        assert_eq!(1, calculator.cycles().count());
+        assert!(matches!(calculator.plan(), Err(PlanError::Cycle(_))));
+    }
+
+    #[test]
+    fn avoid_reverse_conversion() {
+        let smelt_iron = Recipe::new(
+            Stack::new("Iron Ingot", 1),
+            "Furnace",
+            vec![Stack::new("Iron Ore", 1)],
+        );
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(iron_recipes());
+        calculator.add_recipes(vec![smelt_iron.clone()]);
+        for strategy in [RecipeStrategy::FewestRawMaterials, RecipeStrategy::First] {
+            calculator.set_strategy(strategy);
+            calculator.set_target(Stack::new("Iron Block", 1));
+            assert_eq!(0, calculator.cycles().count());
+            assert!(calculator.steps().any(|step| step == (&smelt_iron, 9)));
+        }
+    }
+
+    #[test]
+    fn plan_targets_together() {
+        let planks = Recipe::new(
+            Stack::new("Oak Wood Planks", 4),
+            "Crafting Table",
+            vec![Stack::new("Oak Log", 1)],
+        );
+        let sticks = Recipe::new(
+            Stack::new("Stick", 4),
+            "Crafting Table",
+            vec![Stack::new("Oak Wood Planks", 2)],
+        );
+        let expected = [
+            (
+                &Recipe::new(
+                    Stack::new("Oak Log", 1),
+                    "Raw Material",
+                    vec![Stack::new("Oak Log", 1)],
+                ),
+                1,
+            ),
+            (&planks, 1),
+            (&sticks, 1),
+        ];
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![planks.clone(), sticks.clone()]);
+        calculator.add_target(Stack::new("Stick", 4));
+        calculator.add_target(Stack::new("Oak Wood Planks", 1));
+        calculator.add_target(Stack::new("Oak Wood Planks", 1));
+        assert_eq!(
+            &[Stack::new("Stick", 4), Stack::new("Oak Wood Planks", 2)],
+            calculator.targets(),
+        );
+        let actual = calculator.steps().collect::<Vec<_>>();
+        assert_eq!(&expected[..], &actual[..]);
+        assert_eq!(
+            Some(Stack::new("Stick", 4)),
+            calculator.remove_target("Stick")
+        );
+        assert_eq!(None, calculator.remove_target("Stick"));
+        calculator.clear_targets();
+        assert_eq!(0, calculator.steps().count());
+    }
+
+    #[test]
+    fn order_steps_by_stage_and_item() {
+        let mut recipes = stick_recipes();
+        recipes.push(Recipe::new(
+            Stack::new("Charcoal", 1),
+            "Furnace",
+            vec![Stack::new("Oak Log", 1)],
+        ));
+        recipes.push(Recipe::new(
+            Stack::new("Torch", 4),
+            "Crafting Table",
+            vec![Stack::new("Stick", 1), Stack::new("Charcoal", 1)],
+        ));
+        for _ in 0..10 {
+            let mut calculator = Calculator::new();
+            calculator.add_recipes(recipes.clone());
+            calculator.set_target(Stack::new("Torch", 4));
+            assert_eq!(
+                vec![
+                    ("Oak Log", 2),
+                    ("Charcoal", 1),
+                    ("Oak Wood Planks", 1),
+                    ("Stick", 1),
+                    ("Torch", 1),
+                ],
+                calculator
+                    .steps()
+                    .map(|(recipe, repeats)| (recipe.result().item(), repeats))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn group_steps_into_stages() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(stick_recipes());
+        calculator.add_recipes(vec![
+            Recipe::new(
+                Stack::new("Charcoal", 1),
+                "Furnace",
+                vec![Stack::new("Oak Log", 1)],
+            ),
+            Recipe::new(
+                Stack::new("Torch", 4),
+                "Crafting Table",
+                vec![Stack::new("Stick", 1), Stack::new("Charcoal", 1)],
+            ),
+        ]);
+        calculator.add_resource(Stack::new("Bamboo", 2));
+        calculator.set_strategy(RecipeStrategy::FewestSteps);
+        calculator.set_target(Stack::new("Torch", 4));
+        // The bamboo is taken from storage in the same stage as the logs are gathered, and the
+        // sticks don't wait for the charcoal since they don't need it.
+        assert_eq!(
+            vec![
+                vec![("Raw Material", "Oak Log"), ("In Storage", "Bamboo")],
+                vec![("Furnace", "Charcoal"), ("Crafting Table", "Stick")],
+                vec![("Crafting Table", "Torch")],
+            ],
+            calculator
+                .plan()
+                .unwrap()
+                .stages()
+                .map(|stage| stage
+                    .map(|(recipe, _)| (recipe.method(), recipe.result().item()))
+                    .collect())
+                .collect::<Vec<Vec<_>>>()
+        );
+        assert_eq!(5, calculator.steps().count());
+    }
+
+    #[test]
+    fn calculate_catalysts() {
+        let press = Recipe::new(
+            Stack::new("Iron Plate", 1),
+            "Press",
+            vec![Stack::new("Iron Ingot", 1)],
+        )
+        .with_catalysts(vec![Stack::new("Plate Mold", 1)]);
+        let raw = |item, count| {
+            (
+                Recipe::new(
+                    Stack::new(item, 1),
+                    "Raw Material",
+                    vec![Stack::new(item, 1)],
+                ),
+                count,
+            )
+        };
+        let mut calculator = Calculator::new();
+        calculator.set_recipe(press.clone()).unwrap();
+        calculator.set_target(Stack::new("Iron Plate", 4));
+        let actual = calculator
+            .steps()
+            .map(|(recipe, count)| (recipe.clone(), count))
+            .collect::<Vec<_>>();
+        assert_eq!(3, actual.len());
+        assert!(actual.contains(&raw("Iron Ingot", 4)));
+        assert!(actual.contains(&raw("Plate Mold", 1)));
+        assert_eq!((press.clone(), 4), actual[2]);
+        assert_eq!(
+            vec![Stack::new("Plate Mold", 1)],
+            calculator.catalysts().collect::<Vec<_>>(),
+        );
+        calculator.set_parallel_machines(3);
+        assert!(calculator
+            .steps()
+            .any(|(recipe, count)| (recipe, count) == (&raw("Plate Mold", 1).0, 3)));
+        calculator.add_resource(Stack::new("Plate Mold", 1));
+        assert!(calculator
+            .steps()
+            .any(|(recipe, count)| (recipe, count) == (&raw("Plate Mold", 1).0, 2)));
+    }
+
+    #[test]
+    fn calculate_tool_durability() {
+        let butcher = Recipe::new(
+            Stack::new("Raw Beef", 1),
+            "Butchering",
+            vec![Stack::new("Cow Carcass", 1)],
+        )
+        .with_tools(vec![Tool::new("Knife", 2, 5)]);
+        let skin = Recipe::new(
+            Stack::new("Leather", 1),
+            "Butchering",
+            vec![Stack::new("Cow Carcass", 1)],
+        )
+        .with_tools(vec![Tool::new("Knife", 1, 5)]);
+        let knives = |count| {
+            (
+                Recipe::new(
+                    Stack::new("Knife", 1),
+                    "Raw Material",
+                    vec![Stack::new("Knife", 1)],
+                ),
+                count,
+            )
+        };
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![butcher, skin]);
+        calculator.add_target(Stack::new("Raw Beef", 3));
+        calculator.add_target(Stack::new("Leather", 3));
+        let actual = calculator
+            .steps()
+            .map(|(recipe, count)| (recipe.clone(), count))
+            .collect::<Vec<_>>();
+        assert!(actual.contains(&knives(2)));
+        assert_eq!(
+            vec![("Knife", 1)],
+            calculator.worn_tools().collect::<Vec<_>>()
+        );
+        calculator.add_resource(Stack::new("Knife", 1));
+        assert!(calculator
+            .steps()
+            .any(|(recipe, count)| (recipe, count) == (&knives(1).0, 1)));
+    }
+
+    #[test]
+    fn calculate_uncertain_outputs() {
+        let sieve = Recipe::new(
+            Stack::new("Flint", 1),
+            "Sieve",
+            vec![Stack::new("Gravel", 1)],
+        )
+        .with_chance("Flint", Chance::from_percent(25.0));
+        let mut calculator = Calculator::new();
+        calculator.set_recipe(sieve.clone()).unwrap();
+        calculator.set_target(Stack::new("Flint", 2));
+        assert!(calculator.steps().any(|step| step == (&sieve, 8)));
+        calculator.set_chance_mode(ChanceMode::Guaranteed(Chance::from_percent(90.0)));
+        let (_, repeats) = calculator
+            .steps()
+            .find(|(recipe, _)| *recipe == &sieve)
+            .unwrap();
+        assert!(repeats > 8);
+        assert!(sieve.produced("Flint", repeats, calculator.chance_mode()) >= 2);
+        assert!(sieve.produced("Flint", repeats - 1, calculator.chance_mode()) < 2);
+    }
+
+    #[test]
+    fn calculate_rates() {
+        let circuit = Recipe::new(
+            Stack::new("Electronic Circuit", 1),
+            "Assembler",
+            vec![Stack::new("Iron Plate", 1), Stack::new("Copper Cable", 3)],
+        )
+        .with_duration(Duration::from_millis(500));
+        let cable = Recipe::new(
+            Stack::new("Copper Cable", 2),
+            "Assembler",
+            vec![Stack::new("Copper Plate", 1)],
+        )
+        .with_duration(Duration::from_millis(500));
+        let smelt_iron = Recipe::new(
+            Stack::new("Iron Plate", 1),
+            "Furnace",
+            vec![Stack::new("Iron Ore", 1)],
+        )
+        .with_duration(Duration::from_millis(3200));
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![circuit, cable, smelt_iron]);
+        let plan = calculator.rates(&[("Electronic Circuit", 60.0)]).unwrap();
+        let executions = plan
+            .steps()
+            .iter()
+            .map(|step| (step.recipe().result().item(), step.executions_per_minute()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(60.0, executions["Electronic Circuit"]);
+        assert_eq!(90.0, executions["Copper Cable"]);
+        assert_eq!(90.0, executions["Copper Plate"]);
+        assert_eq!(60.0, executions["Iron Ore"]);
+        let furnaces = plan
+            .steps()
+            .iter()
+            .find(|step| step.recipe().method() == "Furnace")
+            .unwrap();
+        assert!((furnaces.machines().unwrap() - 3.2).abs() < 1e-9);
+        assert_eq!(Some(4), furnaces.machines_needed());
+        assert_eq!(
+            BTreeMap::from([("Assembler", 2), ("Furnace", 4)]),
+            plan.machines(),
+        );
+    }
 }
@@ -0,0 +1,164 @@
+use std::fmt::{self, Display, Formatter};
+
+use nom::{
+    bytes::complete as bytes, character::complete as character, combinator as comb, number,
+    sequence, IResult,
+};
+
+use crate::Count;
+
+/// The probability that something happens, stored in hundredths of a percent.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Chance(u16);
+
+impl Chance {
+    /// Something that always happens.
+    pub const CERTAIN: Self = Self(10_000);
+
+    /// Makes a chance of `percent`%. The chance is clamped to between 0.01% and 100% and rounded
+    /// to the nearest 0.01%.
+    pub fn from_percent(percent: f64) -> Self {
+        Self((percent * 100.0).round().clamp(1.0, 10_000.0) as u16)
+    }
+}
+
+impl Chance {
+    /// The chance as a percentage.
+    pub fn percent(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+
+    /// The chance as a probability between 0 and 1.
+    pub fn probability(self) -> f64 {
+        f64::from(self.0) / 10_000.0
+    }
+
+    /// Whether this chance is [`Chance::CERTAIN`].
+    pub fn is_certain(self) -> bool {
+        self == Self::CERTAIN
+    }
+}
+
+impl Chance {
+    pub(crate) fn nom_parse(s: &str) -> IResult<&str, Self> {
+        comb::map(
+            sequence::terminated(number::complete::double, character::char('%')),
+            Self::from_percent,
+        )(s)
+    }
+
+    /// Parses a chance in the form ` [30%]`, as it appears after an output in a recipe.
+    pub(crate) fn nom_parse_annotation(s: &str) -> IResult<&str, Self> {
+        sequence::delimited(bytes::tag(" ["), Self::nom_parse, bytes::tag("]"))(s)
+    }
+}
+
+impl Default for Chance {
+    fn default() -> Self {
+        Self::CERTAIN
+    }
+}
+
+impl Display for Chance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.percent())
+    }
+}
+
+/// The highest confidence that [`ChanceMode::Guaranteed`] plans for.
+const MAX_CONFIDENCE: f64 = 0.9999;
+
+/// How the calculator plans for outputs that are only produced some of the time.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ChanceMode {
+    /// Plan for the expected amount of each output, rounded down.
+    #[default]
+    Expected,
+    /// Plan for the amount of each output that is produced with at least the given confidence.
+    /// Since nothing uncertain is ever completely guaranteed, confidence above 99.99% is treated
+    /// as 99.99%.
+    Guaranteed(Chance),
+}
+
+impl ChanceMode {
+    /// The amount of an output that can be relied on when `repeats` executions each produce
+    /// `count` items with probability `chance`.
+    pub fn produced(self, count: Count, chance: Chance, repeats: Count) -> Count {
+        if chance.is_certain() {
+            return count * repeats;
+        }
+        match self {
+            Self::Expected => {
+                // Nudge the result up a little so that exact products like `10 * 0.3` aren't
+                // rounded down because of floating point error.
+                (count as f64 * repeats as f64 * chance.probability() + 1e-9).floor() as Count
+            }
+            Self::Guaranteed(confidence) => {
+                // Find the largest number of successes that happens at least as often as the
+                // confidence requires, working in log space so that large numbers of repeats
+                // don't underflow.
+                let p = chance.probability();
+                let log_odds = (p / (1.0 - p)).ln();
+                let mut log_pmf = repeats as f64 * (1.0 - p).ln();
+                let mut fewer = 0.0;
+                let mut successes = 0;
+                while successes < repeats {
+                    fewer += log_pmf.exp();
+                    if 1.0 - fewer < confidence.probability().min(MAX_CONFIDENCE) {
+                        break;
+                    }
+                    log_pmf +=
+                        ((repeats - successes) as f64 / (successes + 1) as f64).ln() + log_odds;
+                    successes += 1;
+                }
+                successes * count
+            }
+        }
+    }
+
+    /// The smallest number of repeats for which [`self.produced()`] is at least `needed`.
+    ///
+    /// [`self.produced()`]: #method.produced
+    pub fn repeats_for(self, needed: Count, count: Count, chance: Chance) -> Count {
+        let count = count.max(1);
+        let mut low = 1;
+        let mut high = (needed as f64 / (count as f64 * chance.probability())).ceil() as Count;
+        high = high.max(1);
+        while self.produced(count, chance, high) < needed {
+            low = high + 1;
+            high *= 2;
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.produced(count, chance, mid) >= needed {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        high
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_production() {
+        let chance = Chance::from_percent(30.0);
+        assert_eq!(3, ChanceMode::Expected.produced(1, chance, 10));
+        assert_eq!(10, ChanceMode::Expected.repeats_for(3, 1, chance));
+        assert_eq!(9, ChanceMode::Expected.repeats_for(5, 2, chance));
+    }
+
+    #[test]
+    fn guaranteed_production() {
+        let chance = Chance::from_percent(50.0);
+        let mode = ChanceMode::Guaranteed(Chance::from_percent(75.0));
+        // 1 - 0.5^2 = 75% chance of at least one success in two attempts.
+        assert_eq!(1, mode.produced(1, chance, 2));
+        assert_eq!(2, mode.repeats_for(1, 1, chance));
+        assert!(mode.repeats_for(10, 1, chance) > ChanceMode::Expected.repeats_for(10, 1, chance));
+    }
+}
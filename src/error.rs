@@ -0,0 +1,259 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+/// An error encountered while parsing text, along with where it happened.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    expected: &'static str,
+    line: usize,
+    column: usize,
+}
+
+impl ParseError {
+    /// Makes an error for failing to parse `expected` at the start of `rest`, which must be the
+    /// end of `input`.
+    pub(crate) fn new(input: &str, rest: &str, expected: &'static str) -> Self {
+        Self::at(position(input, rest), expected)
+    }
+
+    /// Makes an error for failing to parse `expected` at a known line and column.
+    pub(crate) fn at((line, column): (usize, usize), expected: &'static str) -> Self {
+        Self {
+            expected,
+            line,
+            column,
+        }
+    }
+
+    /// A description of what was expected where parsing failed.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// The line where parsing failed, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column where parsing failed, in characters starting from 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} at line {}, column {}",
+            self.expected, self.line, self.column
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// The line and column, both starting from 1, of the start of `rest`, which must be the end of
+/// `input`.
+pub(crate) fn position(input: &str, rest: &str) -> (usize, usize) {
+    let parsed = &input[..input.len() - rest.len()];
+    let line_start = parsed.rfind('\n').map_or(0, |idx| idx + 1);
+    (
+        parsed.matches('\n').count() + 1,
+        parsed[line_start..].chars().count() + 1,
+    )
+}
+
+/// Finds the line and column of positions in `input` which are visited from start to end, without
+/// going back over the text before the last position found.
+pub(crate) struct Cursor<'a> {
+    input: &'a str,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            offset: 0,
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    /// The line and column, both starting from 1, of the start of `rest`, which must be the end of
+    /// the input and must not start before the last position found.
+    pub(crate) fn position(&mut self, rest: &str) -> (usize, usize) {
+        let offset = self.input.len() - rest.len();
+        let skipped = &self.input[self.offset..offset];
+        if let Some(idx) = skipped.rfind('\n') {
+            self.line += skipped.matches('\n').count();
+            self.line_start = self.offset + idx + 1;
+        }
+        self.offset = offset;
+        (
+            self.line,
+            self.input[self.line_start..offset].chars().count() + 1,
+        )
+    }
+}
+
+/// A problem with a recipe that keeps it from being used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RecipeError {
+    /// The recipe produces or needs none of `item`.
+    ZeroCount {
+        /// The item produced by the recipe.
+        recipe: String,
+        /// The item with a count of 0.
+        item: String,
+    },
+    /// The recipe doesn't need anything, so it would produce items out of nothing.
+    NoIngredients {
+        /// The item produced by the recipe.
+        recipe: String,
+    },
+    /// The recipe needs the item that it produces.
+    SelfReference {
+        /// The item produced by the recipe.
+        recipe: String,
+    },
+    /// The recipe lists `item` more than once among the things it needs.
+    DuplicateIngredient {
+        /// The item produced by the recipe.
+        recipe: String,
+        /// The item that is listed more than once.
+        item: String,
+    },
+    /// The recipe lists `item` more than once among the things it produces.
+    DuplicateOutput {
+        /// The item produced by the recipe.
+        recipe: String,
+        /// The item that is listed more than once.
+        item: String,
+    },
+    /// The recipe uses `tool`, but the tool wears out without being used even once.
+    ZeroDurability {
+        /// The item produced by the recipe.
+        recipe: String,
+        /// The tool with a durability of 0.
+        tool: String,
+    },
+    /// The recipe uses a method that the calculator uses for its own steps.
+    ReservedMethod {
+        /// The item produced by the recipe.
+        recipe: String,
+        /// The reserved method.
+        method: String,
+    },
+}
+
+impl Display for RecipeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroCount { recipe, item } => {
+                write!(f, "the recipe for {recipe} has 0 of {item}")
+            }
+            Self::NoIngredients { recipe } => {
+                write!(f, "the recipe for {recipe} doesn't have any ingredients")
+            }
+            Self::SelfReference { recipe } => {
+                write!(f, "the recipe for {recipe} needs {recipe} itself")
+            }
+            Self::DuplicateIngredient { recipe, item } => {
+                write!(f, "the recipe for {recipe} lists {item} more than once")
+            }
+            Self::DuplicateOutput { recipe, item } => {
+                write!(f, "the recipe for {recipe} produces {item} more than once")
+            }
+            Self::ZeroDurability { recipe, tool } => {
+                write!(
+                    f,
+                    "the recipe for {recipe} uses {tool}, which has a durability of 0"
+                )
+            }
+            Self::ReservedMethod { recipe, method } => write!(
+                f,
+                "the recipe for {recipe} uses {method:?}, which is reserved by the calculator"
+            ),
+        }
+    }
+}
+
+impl Error for RecipeError {}
+
+/// A problem with a recipe, along with the line where the recipe starts if it was loaded from
+/// text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    error: RecipeError,
+    line: Option<usize>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(error: RecipeError, line: Option<usize>) -> Self {
+        Self { error, line }
+    }
+
+    /// The problem with the recipe.
+    pub fn error(&self) -> &RecipeError {
+        &self.error
+    }
+
+    /// The line where the recipe starts, starting from 1, if it is known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.error),
+            None => self.error.fmt(f),
+        }
+    }
+}
+
+impl Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A reason the calculator couldn't plan for its targets.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlanError {
+    /// The recipes needed for the targets form a cycle and the calculator's
+    /// [`CyclePolicy`](crate::CyclePolicy) doesn't allow breaking it. The items involved are
+    /// listed starting with the item furthest up the recipe tree.
+    Cycle(Vec<String>),
+    /// There is no recipe at `index` among the recipes for `item`. Items without any recipe aren't
+    /// an error; they are planned as raw materials.
+    NoSuchRecipe {
+        /// The item that the recipe was supposed to produce.
+        item: String,
+        /// The index of the recipe among the recipes for the item.
+        index: usize,
+    },
+    /// A target asks for none of an item.
+    ZeroCount(String),
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cycle(items) => write!(f, "the recipes form a cycle: {}", items.join(" -> ")),
+            Self::NoSuchRecipe { item, index } => {
+                write!(f, "there is no recipe #{} for {item}", index + 1)
+            }
+            Self::ZeroCount(item) => write!(f, "the target for {item} has a count of 0"),
+        }
+    }
+}
+
+impl Error for PlanError {}
@@ -8,10 +8,28 @@
 mod calculator;
 pub use calculator::*;
 
+mod chance;
+pub use chance::*;
+
 mod stack;
 pub use stack::*;
 
+mod error;
+pub use error::*;
+
+mod optimizer;
+pub use optimizer::*;
+
+mod plan;
+pub use plan::*;
+
+mod rates;
+pub use rates::*;
+
 mod recipe;
 pub use recipe::*;
 
+mod tool;
+pub use tool::*;
+
 mod util;
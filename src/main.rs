@@ -8,10 +8,12 @@
 use std::{
     fs::{File, OpenOptions},
     io::{self, Read, Write as IoWrite},
+    time::Duration,
 };
 
-use crafting_calculator::{Calculator, Recipe};
-use nom::Parser;
+use crafting_calculator::{
+    Calculator, Chance, ChanceMode, CyclePolicy, Objective, Recipe, RecipeStrategy, Stack,
+};
 
 fn read_line() -> io::Result<String> {
     let mut line = String::new();
@@ -42,6 +44,363 @@ trait Command {
     fn long_help(&self) -> &'static str {
         self.short_help()
     }
+
+    /// Whether the command has to be typed out in full rather than shortened to a prefix, for
+    /// commands that are hard to undo.
+    fn needs_full_name(&self) -> bool {
+        false
+    }
+}
+
+struct Alternatives;
+
+impl Command for Alternatives {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let calculator = &mut state.calculator;
+        let (item, choice) = match arguments.rsplit_once(' ') {
+            Some((item, choice)) => match choice.parse::<usize>() {
+                Ok(choice) => (item.trim(), Some(choice)),
+                Err(_) => (arguments, None),
+            },
+            None => (arguments, None),
+        };
+        if item.is_empty() {
+            eprintln!("Can't list alternatives with no `item` argument.");
+            return;
+        }
+        match choice {
+            None => {
+                let preferred = calculator.preferred_recipe(item);
+                for (idx, recipe) in calculator.recipes_for(item).enumerate() {
+                    let marker = if preferred == Some(idx) { "*" } else { " " };
+                    print!("{marker}{}. {recipe}", idx + 1);
+                }
+            }
+            Some(0) => calculator.clear_preference(item),
+            Some(choice) => {
+                if let Err(e) = calculator.prefer_recipe(item, choice - 1) {
+                    eprintln!("Couldn't use that recipe: {e}");
+                }
+            }
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "alternatives <item> [n]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "List the recipes for `item` or always use the `n`th one."
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "If `n` is omitted, lists the recipes that produce `item`, marking the pinned one with ",
+            "`*`. Otherwise, pins recipe number `n` as the one to use for `item` regardless of the ",
+            "current strategy. If `n` is 0, the pin is removed.",
+        )
+    }
+}
+
+struct Chances;
+
+impl Command for Chances {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let mode = match arguments {
+            "" => {
+                match state.calculator.chance_mode() {
+                    ChanceMode::Expected => println!("expected"),
+                    ChanceMode::Guaranteed(confidence) => println!("{confidence}"),
+                }
+                return;
+            }
+            "expected" => ChanceMode::Expected,
+            _ => match arguments.strip_suffix('%').map(str::parse) {
+                Some(Ok(percent)) => ChanceMode::Guaranteed(Chance::from_percent(percent)),
+                _ => {
+                    eprintln!("Unknown chance mode: {arguments:?}");
+                    return;
+                }
+            },
+        };
+        state.calculator.set_chance_mode(mode);
+    }
+
+    fn example(&self) -> &'static str {
+        "chances [mode]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Sets how to plan for uncertain outputs or prints the current mode"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Sets how the calculator plans for outputs that are only produced some of the time. ",
+            "`mode` can be `expected` (plan for the expected amount) or a confidence like `95%` ",
+            "(plan for the amount that is produced at least that often). If `mode` is omitted, ",
+            "prints the current mode.",
+        )
+    }
+}
+
+struct Checklist;
+
+impl Command for Checklist {
+    fn apply(&self, _arguments: &str, state: &mut State) {
+        let calculator = &mut state.calculator;
+        let mut skipped = vec![];
+        loop {
+            if let Err(e) = calculator.plan() {
+                eprintln!("Couldn't plan steps: {e}");
+                return;
+            }
+            // Taking items out of storage doesn't need to be checked off.
+            let next_step = calculator
+                .steps()
+                .find(|&(recipe, _)| recipe.method() != "In Storage" && !skipped.contains(recipe))
+                .map(|(recipe, repeats)| (recipe.clone(), repeats));
+            let Some((recipe, repeats)) = next_step else {
+                break;
+            };
+            print!("{recipe:.repeats$}");
+            let answer = match prompt("[d]one, [p]artial <n>, [s]kip, or [q]uit") {
+                Ok(answer) => answer,
+                Err(e) => {
+                    eprintln!("Couldn't get answer: {e:?}");
+                    return;
+                }
+            };
+            let mut words = answer.split_whitespace();
+            match (words.next(), words.next()) {
+                (None | Some("d" | "done"), None) => calculator.apply_step(&recipe, repeats),
+                (Some("p" | "partial"), Some(done)) => match done.parse::<usize>() {
+                    Ok(done) if done <= repeats => calculator.apply_step(&recipe, done),
+                    Ok(_) => eprintln!("The step only needs to be done {repeats} times"),
+                    Err(e) => eprintln!("Couldn't parse the number of times done: {e:?}"),
+                },
+                (Some("s" | "skip"), None) => skipped.push(recipe),
+                (Some("q" | "quit"), None) => return,
+                _ => eprintln!("Unknown answer: {answer:?}"),
+            }
+        }
+        if skipped.is_empty() {
+            println!("All steps are done and the targets are in the resources");
+            calculator.clear_targets();
+        } else {
+            println!("All steps have been done or skipped");
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "checklist"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Walks through the current plan one step at a time, updating the resources as it goes"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Walks through the current plan one step at a time. Each step can be marked as done, ",
+            "partially done by giving how many times it was done, or skipped. The resources are ",
+            "updated after each step and the rest of the plan is recalculated from them. Once ",
+            "every step has been done, the targets are cleared since they are in the resources.",
+        )
+    }
+}
+
+struct Cost;
+
+impl Command for Cost {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let (item, cost) = match arguments.rsplit_once(' ') {
+            Some((item, cost)) => (item.trim(), cost),
+            None => (arguments, ""),
+        };
+        if item.is_empty() {
+            eprintln!("Missing item");
+            return;
+        }
+        if cost.is_empty() {
+            println!("{}", state.calculator.raw_cost(item));
+            return;
+        }
+        match cost.parse::<f64>() {
+            Ok(cost) if cost >= 0.0 => state.calculator.set_raw_cost(item, cost),
+            Ok(_) => eprintln!("Cost can't be negative"),
+            Err(_) => {
+                // The last word is part of the item name.
+                println!("{}", state.calculator.raw_cost(arguments));
+            }
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "cost <item> [cost]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Sets the cost of gathering `item` as a raw material or prints the current cost"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Sets the cost of gathering a single `item` as a raw material, which is used by the ",
+            "`optimal-cost` strategy. Raw materials cost 1 unless set otherwise. If `cost` is ",
+            "omitted, prints the current cost.",
+        )
+    }
+}
+
+struct Craftable;
+
+impl Command for Craftable {
+    fn apply(&self, _arguments: &str, state: &mut State) {
+        let craftable = match state.calculator.craftable_items() {
+            Ok(craftable) => craftable,
+            Err(e) => {
+                eprintln!("Couldn't plan for the craftable items: {e}");
+                return;
+            }
+        };
+        if craftable.is_empty() {
+            println!("Nothing can be crafted from the available resources");
+        }
+        for item in craftable {
+            match item.count() {
+                Some(count) => println!(
+                    "{} [depth {}]",
+                    Stack::new(item.item(), count),
+                    item.depth()
+                ),
+                None => println!("{} (unlimited) [depth {}]", item.item(), item.depth()),
+            }
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "craftable"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Lists everything that can be crafted from the resources alone"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Lists every item that can be crafted from the available resources without gathering ",
+            "anything, along with the most of each that can be made and how many steps need to ",
+            "happen one after another to make it.",
+        )
+    }
+}
+
+struct Cycles;
+
+impl Command for Cycles {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let policy = match arguments {
+            "" => {
+                for cycle in state.calculator.cycles() {
+                    println!("{} -> {}", cycle.join(" -> "), cycle[0]);
+                }
+                return;
+            }
+            "raw" => CyclePolicy::TreatAsRaw,
+            "fail" => CyclePolicy::Fail,
+            _ => {
+                eprintln!("Unknown cycle policy: {arguments:?}");
+                return;
+            }
+        };
+        state.calculator.set_cycle_policy(policy);
+    }
+
+    fn example(&self) -> &'static str {
+        "cycles [policy]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Sets how to handle recipe cycles or prints the cycles in the current plan"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Sets what the calculator does when the recipes it would use form a cycle. ",
+            "`policy` can be `raw` (treat the item that closes the cycle as a raw material) or ",
+            "`fail` (don't produce any steps). If `policy` is omitted, prints the cycles that were ",
+            "found in the current plan.",
+        )
+    }
+}
+
+struct Done;
+
+impl Command for Done {
+    fn apply(&self, _arguments: &str, state: &mut State) {
+        if let Err(e) = state.calculator.commit_plan() {
+            eprintln!("Couldn't apply the plan: {e}");
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "done"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Marks the current plan as crafted and updates the resources to match"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Marks every step of the current plan as crafted. The materials taken from storage ",
+            "are removed from the resources, the targets, leftovers and catalysts are added to ",
+            "them, tools that aren't used up are kept with their remaining uses, and the targets ",
+            "are cleared. Since this can't be undone, it can't be shortened.",
+        )
+    }
+
+    fn needs_full_name(&self) -> bool {
+        true
+    }
+}
+
+struct Gather;
+
+impl Command for Gather {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let calculator = &mut state.calculator;
+        if arguments.is_empty() {
+            for item in calculator.always_raw() {
+                println!("{item}");
+            }
+            return;
+        }
+        let raw = !calculator.is_always_raw(arguments);
+        calculator.set_always_raw(arguments, raw);
+        if raw {
+            println!("{arguments:?} will be gathered as a raw material");
+        } else {
+            println!("{arguments:?} will be crafted");
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "gather [item]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Toggles whether `item` is always gathered as a raw material or lists those items"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Toggles whether `item` is always gathered as a raw material instead of being ",
+            "crafted, like when it is farmed or bought. The recipes for `item` are kept for when ",
+            "it is toggled back. If `item` is omitted, lists the items that are always gathered.",
+        )
+    }
 }
 
 struct Help;
@@ -97,55 +456,178 @@ impl Command for Load {
                 return;
             }
         };
-        let recipes = {
-            let mut s = String::new();
-            match f.read_to_string(&mut s) {
+        let mut s = String::new();
+        match f.read_to_string(&mut s) {
+            Ok(_) => {}
+            Err(e) => eprintln!("Couldn't read recipe file {filename:?}: {e:?}"),
+        }
+        let (diagnostics, errors) = calculator.load_recipes(&s, "Crafting Table");
+        for e in errors {
+            eprintln!("Skipping unreadable recipe in {filename:?}: {e}");
+        }
+        for diagnostic in diagnostics {
+            eprintln!("Skipping invalid recipe in {filename:?} at {diagnostic}");
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "load <file>"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Read recipes from `file`."
+    }
+}
+
+fn write_steps(out: &mut dyn IoWrite, calculator: &mut Calculator, by_stage: bool) {
+    let plan = match calculator.plan() {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Couldn't plan steps: {e}");
+            return;
+        }
+    };
+    let mode = calculator.chance_mode();
+    let planned_for = match mode {
+        ChanceMode::Expected => "expected".to_string(),
+        ChanceMode::Guaranteed(confidence) => format!("guaranteed at {confidence}"),
+    };
+    for (stage, steps) in plan.stages().enumerate() {
+        if by_stage {
+            match writeln!(out, "Stage {}:", stage + 1) {
                 Ok(_) => {}
-                Err(e) => eprintln!("Couldn't read recipe file {filename:?}: {e:?}"),
+                Err(e) => {
+                    eprintln!("Couldn't write steps: {e:?}");
+                    return;
+                }
+            }
+        }
+        for (recipe, count) in steps {
+            match write!(out, "{recipe:.count$}") {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Couldn't write steps: {e:?}");
+                    return;
+                }
             }
-            match Recipe::parse_recipes("Crafting Table").parse(&s) {
-                Ok(("", recipes)) => recipes,
-                Ok((junk, recipes)) => {
-                    eprintln!("Found junk data {junk:?} at the end of the recipe file");
-                    recipes
+            for (output, chance) in recipe.outputs_with_chances() {
+                if chance.is_certain() {
+                    continue;
                 }
+                let produced = recipe.produced(output.item(), count, mode);
+                match writeln!(out, "    ({planned_for}: {} ({produced}))", output.item()) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("Couldn't write steps: {e:?}");
+                        return;
+                    }
+                }
+            }
+            match writeln!(out) {
+                Ok(_) => {}
                 Err(e) => {
-                    let e = io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}"));
-                    eprintln!("Couldn't parse recipe file {filename:?}: {e:?}");
+                    eprintln!("Couldn't write steps: {e:?}");
                     return;
                 }
             }
-        };
-        calculator.add_recipes(recipes);
+        }
     }
+    let mut uncraftable = calculator.uncraftable().peekable();
+    if uncraftable.peek().is_some() {
+        match writeln!(out, "Can't be crafted with the available methods:") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write steps: {e:?}");
+                return;
+            }
+        }
+    }
+    for item in uncraftable {
+        match writeln!(out, "    {item}") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write steps: {e:?}");
+                return;
+            }
+        }
+    }
+    let mut catalysts = calculator.catalysts().peekable();
+    if catalysts.peek().is_some() {
+        match writeln!(out, "Catalysts:") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write steps: {e:?}");
+                return;
+            }
+        }
+    }
+    for catalyst in catalysts {
+        match writeln!(out, "    {catalyst}") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write steps: {e:?}");
+                return;
+            }
+        }
+    }
+    let mut worn_tools = calculator.worn_tools().peekable();
+    if worn_tools.peek().is_some() {
+        match writeln!(out, "Partially used tools:") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write steps: {e:?}");
+                return;
+            }
+        }
+    }
+    for (tool, uses_left) in worn_tools {
+        match writeln!(out, "    {tool} ({uses_left} uses left)") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write steps: {e:?}");
+                return;
+            }
+        }
+    }
+}
 
-    fn example(&self) -> &'static str {
-        "load <file>"
+fn write_resources(out: &mut dyn IoWrite, calculator: &mut Calculator) {
+    for stack in calculator.resources() {
+        match writeln!(out, "{}", stack) {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write resources: {e:?}");
+                return;
+            }
+        }
     }
-
-    fn short_help(&self) -> &'static str {
-        "Read recipes from `file`."
+    let mut worn_tools = calculator.partly_used_tools().peekable();
+    if worn_tools.peek().is_some() {
+        match writeln!(out, "Partially used tools:") {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Couldn't write resources: {e:?}");
+                return;
+            }
+        }
     }
-}
-
-fn write_steps(out: &mut dyn IoWrite, calculator: &mut Calculator) {
-    for (recipe, count) in calculator.steps() {
-        match writeln!(out, "{recipe:.count$}") {
+    for (tool, uses_left) in worn_tools {
+        match writeln!(out, "    {tool} ({uses_left} uses left)") {
             Ok(_) => {}
             Err(e) => {
-                eprintln!("Couldn't write steps: {e:?}");
+                eprintln!("Couldn't write resources: {e:?}");
                 return;
             }
         }
     }
 }
 
-fn write_resources(out: &mut dyn IoWrite, calculator: &mut Calculator) {
-    for stack in calculator.resources() {
-        match writeln!(out, "{}", stack) {
+fn write_stacks(out: &mut dyn IoWrite, stacks: impl Iterator<Item = Stack>, what: &str) {
+    for stack in stacks {
+        match writeln!(out, "{stack}") {
             Ok(_) => {}
             Err(e) => {
-                eprintln!("Couldn't write resources: {e:?}");
+                eprintln!("Couldn't write {what}: {e:?}");
                 return;
             }
         }
@@ -176,13 +658,56 @@ fn write_recipes(out: &mut dyn IoWrite, calculator: &mut Calculator) {
     }
 }
 
+struct Machines;
+
+impl Command for Machines {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        if arguments.is_empty() {
+            println!("{}", state.calculator.parallel_machines());
+            return;
+        }
+        match arguments.parse() {
+            Ok(machines) => state.calculator.set_parallel_machines(machines),
+            Err(e) => eprintln!("Couldn't parse machine count: {e:?}"),
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "machines [n]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Sets how many machines run each step in parallel or prints the current number"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Sets how many machines run each step in parallel. Catalysts are needed once per ",
+            "machine rather than once per plan. If `n` is omitted, prints the current number.",
+        )
+    }
+}
+
 struct Print;
 
 impl Command for Print {
     fn apply(&self, arguments: &str, state: &mut State) {
         match arguments {
-            "steps" | "" => write_steps(&mut io::stdout().lock(), &mut state.calculator),
+            "steps" | "" => write_steps(&mut io::stdout().lock(), &mut state.calculator, false),
+            "stages" => write_steps(&mut io::stdout().lock(), &mut state.calculator, true),
             "resources" => write_resources(&mut io::stdout().lock(), &mut state.calculator),
+            "raw" => match state.calculator.plan() {
+                Ok(plan) => write_stacks(
+                    &mut io::stdout().lock(),
+                    plan.raw_materials(),
+                    "raw materials",
+                ),
+                Err(e) => eprintln!("Couldn't plan steps: {e}"),
+            },
+            "leftovers" => match state.calculator.plan() {
+                Ok(plan) => write_stacks(&mut io::stdout().lock(), plan.leftovers(), "leftovers"),
+                Err(e) => eprintln!("Couldn't plan steps: {e}"),
+            },
             "recipes" => write_recipes(&mut io::stdout().lock(), &mut state.calculator),
             _ => println!("Unknown `what`: {arguments:?}"),
         }
@@ -199,12 +724,96 @@ impl Command for Print {
     fn long_help(&self) -> &'static str {
         concat!(
             "Print the current state of the calculator.\n",
-            "`what` can be `steps`, `resources`, or `recipes`. ",
+            "`what` can be `steps`, `stages` (the steps grouped into stages that can each be done ",
+            "all at once), `resources`, `recipes`, `raw` (the raw materials to gather), ",
+            "or `leftovers` (the crafted items left over once every step is done). ",
             "If `what` is omitted, it is assumed to be `steps`.",
         )
     }
 }
 
+struct Max;
+
+impl Command for Max {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        if arguments.is_empty() {
+            eprintln!("Can't find the most that can be made with no `item` argument.");
+            return;
+        }
+        let limit = match state.calculator.max_craftable(arguments) {
+            Ok(limit) => limit,
+            Err(e) => {
+                eprintln!("Couldn't plan for {arguments:?}: {e}");
+                return;
+            }
+        };
+        match limit.count() {
+            Some(count) => println!("{}", Stack::new(arguments, count)),
+            None => println!("{arguments} (unlimited), since making it doesn't use anything up"),
+        }
+        if !limit.limited_by().is_empty() {
+            println!("Making one more would need:");
+            for stack in limit.limited_by() {
+                println!("    {stack}");
+            }
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "max <item>"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Prints the most of `item` that can be made from the resources alone"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Prints the most of `item` that can be made from the available resources without ",
+            "gathering anything, including any that are already available, along with the ",
+            "resources that would be needed to make one more.",
+        )
+    }
+}
+
+struct Methods;
+
+impl Command for Methods {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let calculator = &mut state.calculator;
+        match arguments {
+            "" => match calculator.available_methods() {
+                Some(methods) => {
+                    for method in methods {
+                        println!("{method}");
+                    }
+                }
+                None => println!("All methods are available"),
+            },
+            "all" => calculator.allow_all_methods(),
+            _ => calculator.set_available_methods(arguments.split(',').map(str::trim)),
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "methods [method, ...]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Sets which crafting methods are available or prints the available methods"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Only allows recipes which use one of the given comma-separated methods, like ",
+            "`methods Crafting Table, Furnace`. Items which can't be made with those methods are ",
+            "gathered as raw materials instead and flagged when printing the steps. ",
+            "`methods all` allows every method again. If no methods are given, prints the ",
+            "available methods.",
+        )
+    }
+}
+
 struct NewRecipe;
 
 impl Command for NewRecipe {
@@ -213,7 +822,7 @@ impl Command for NewRecipe {
             Ok(s) => match s.parse() {
                 Ok(s) => s,
                 Err(e) => {
-                    eprintln!("Couldn't parse result: {e:?}");
+                    eprintln!("Couldn't parse result: {e}");
                     return;
                 }
             },
@@ -222,6 +831,45 @@ impl Command for NewRecipe {
                 return;
             }
         };
+        let mut byproducts = vec![];
+        loop {
+            match prompt("Enter byproduct (leave blank to finish)") {
+                Ok(s) if s.is_empty() => break,
+                Ok(s) => match s.parse() {
+                    Ok(byproduct) => byproducts.push(byproduct),
+                    Err(e) => {
+                        eprintln!("Couldn't parse byproduct: {e}");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Couldn't get byproduct: {e:?}");
+                    return;
+                }
+            }
+        }
+        let mut chances = vec![];
+        for item in std::iter::once(&result)
+            .chain(&byproducts)
+            .map(|stack: &Stack| stack.item().to_string())
+        {
+            let question =
+                format!("Enter chance of producing {item} (ex: 25%, leave blank if always)");
+            match prompt(&question) {
+                Ok(s) if s.is_empty() => {}
+                Ok(s) => match s.trim_end_matches('%').trim().parse() {
+                    Ok(percent) => chances.push((item, Chance::from_percent(percent))),
+                    Err(e) => {
+                        eprintln!("Couldn't parse chance: {e}");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Couldn't get chance: {e:?}");
+                    return;
+                }
+            }
+        }
         let method = match prompt("Enter crafting method") {
             Ok(s) => s,
             Err(e) => {
@@ -229,6 +877,24 @@ impl Command for NewRecipe {
                 return;
             }
         };
+        let duration = match prompt("Enter duration in seconds (leave blank if unknown)") {
+            Ok(s) if s.is_empty() => None,
+            Ok(s) => match s.parse().map(Duration::try_from_secs_f64) {
+                Ok(Ok(duration)) => Some(duration),
+                Ok(Err(e)) => {
+                    eprintln!("Couldn't parse duration: {e:?}");
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Couldn't parse duration: {e:?}");
+                    return;
+                }
+            },
+            Err(e) => {
+                eprintln!("Couldn't get duration: {e:?}");
+                return;
+            }
+        };
         let mut ingredients = vec![];
         loop {
             match prompt("Enter ingredient (leave blank to finish)") {
@@ -236,7 +902,7 @@ impl Command for NewRecipe {
                 Ok(s) => match s.parse() {
                     Ok(ingredient) => ingredients.push(ingredient),
                     Err(e) => {
-                        eprintln!("Couldn't parse ingredient: {e:?}");
+                        eprintln!("Couldn't parse ingredient: {e}");
                         return;
                     }
                 },
@@ -246,8 +912,53 @@ impl Command for NewRecipe {
                 }
             }
         }
-        let recipe = Recipe::new(result, method, ingredients);
-        state.calculator.set_recipe(recipe);
+        let mut catalysts = vec![];
+        loop {
+            match prompt("Enter catalyst (leave blank to finish)") {
+                Ok(s) if s.is_empty() => break,
+                Ok(s) => match s.parse() {
+                    Ok(catalyst) => catalysts.push(catalyst),
+                    Err(e) => {
+                        eprintln!("Couldn't parse catalyst: {e}");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Couldn't get catalyst: {e:?}");
+                    return;
+                }
+            }
+        }
+        let mut tools = vec![];
+        loop {
+            match prompt("Enter tool (ex: Knife (1) [tool 64], leave blank to finish)") {
+                Ok(s) if s.is_empty() => break,
+                Ok(s) => match s.parse() {
+                    Ok(tool) => tools.push(tool),
+                    Err(e) => {
+                        eprintln!("Couldn't parse tool: {e}");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Couldn't get tool: {e:?}");
+                    return;
+                }
+            }
+        }
+        let mut recipe = Recipe::new(result, method, ingredients)
+            .with_byproducts(byproducts)
+            .with_catalysts(catalysts)
+            .with_tools(tools);
+        if let Some(duration) = duration {
+            recipe = recipe.with_duration(duration);
+        }
+        for (item, chance) in chances {
+            recipe = recipe.with_chance(item, chance);
+        }
+        if let Err(e) = state.calculator.set_recipe(recipe) {
+            eprintln!("Couldn't add recipe: {e}");
+        }
     }
 
     fn example(&self) -> &'static str {
@@ -267,17 +978,40 @@ struct Resource;
 
 impl Command for Resource {
     fn apply(&self, arguments: &str, state: &mut State) {
+        if arguments == "clear" {
+            state.calculator.clear_resources();
+            return;
+        }
+        if let Some(item) = arguments.strip_prefix("remove ") {
+            if let (None, None) = state.calculator.remove_resource(item.trim()) {
+                eprintln!("{:?} isn't a resource", item.trim());
+            }
+            return;
+        }
         macro_rules! parse_resource {
             ($s:ident) => {
                 match $s.parse() {
                     Ok(resource) => resource,
                     Err(e) => {
-                        eprintln!("Couldn't parse resource: {e:?}");
+                        eprintln!("Couldn't parse resource: {e}");
                         return;
                     }
                 }
             };
         }
+        if let Some(arguments) = arguments.strip_prefix("set ") {
+            let resource = parse_resource!(arguments);
+            state.calculator.set_resource(resource);
+            return;
+        }
+        if let Some(arguments) = arguments.strip_prefix("take ") {
+            let resource: Stack = parse_resource!(arguments);
+            let taken = state.calculator.take_resource(resource.clone());
+            if taken < resource.count() {
+                eprintln!("Only {taken} of {:?} were available", resource.item());
+            }
+            return;
+        }
         let resource = if arguments.is_empty() {
             match prompt("Enter resource") {
                 Ok(s) => parse_resource!(s),
@@ -301,7 +1035,65 @@ impl Command for Resource {
     }
 
     fn long_help(&self) -> &'static str {
-        "Adds `stack` as a resource that is already available and therefore does not need to be crafted"
+        concat!(
+            "Adds `stack` as a resource that is already available and therefore does not need to ",
+            "be crafted. If `stack` is omitted, prompts for it.\n",
+            "`resource set <stack>` sets the available amount of the item to exactly `stack`.\n",
+            "`resource take <stack>` takes `stack` out of the available resources.\n",
+            "`resource remove <item>` removes `item` from the available resources.\n",
+            "`resource clear` removes all of the available resources.",
+        )
+    }
+}
+
+struct Strategy;
+
+impl Command for Strategy {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let strategy = match arguments {
+            "" => {
+                let name = match state.calculator.strategy() {
+                    RecipeStrategy::FewestRawMaterials => "raw",
+                    RecipeStrategy::FewestSteps => "steps",
+                    RecipeStrategy::Optimal(Objective::RawMaterials) => "optimal",
+                    RecipeStrategy::Optimal(Objective::WeightedRawMaterials) => "optimal-cost",
+                    RecipeStrategy::Optimal(Objective::CraftingTime) => "optimal-time",
+                    RecipeStrategy::First => "first",
+                };
+                println!("{name}");
+                return;
+            }
+            "raw" => RecipeStrategy::FewestRawMaterials,
+            "steps" => RecipeStrategy::FewestSteps,
+            "optimal" => RecipeStrategy::Optimal(Objective::RawMaterials),
+            "optimal-cost" => RecipeStrategy::Optimal(Objective::WeightedRawMaterials),
+            "optimal-time" => RecipeStrategy::Optimal(Objective::CraftingTime),
+            "first" => RecipeStrategy::First,
+            _ => {
+                eprintln!("Unknown strategy: {arguments:?}");
+                return;
+            }
+        };
+        state.calculator.set_strategy(strategy);
+    }
+
+    fn example(&self) -> &'static str {
+        "strategy [name]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Sets how to choose between recipes for the same item or prints the current strategy"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Sets how the calculator chooses between multiple recipes for the same item. ",
+            "`name` can be `raw` (fewest raw materials), `steps` (fewest crafting steps), ",
+            "`first` (the recipe that was added first), or one of the optimizing strategies, ",
+            "which consider every recipe at once: `optimal` (fewest raw materials overall), ",
+            "`optimal-cost` (cheapest raw materials, see `cost`), and `optimal-time` (least ",
+            "crafting time). If `name` is omitted, prints the current strategy.",
+        )
     }
 }
 
@@ -309,10 +1101,27 @@ struct Target;
 
 impl Command for Target {
     fn apply(&self, arguments: &str, state: &mut State) {
+        let calculator = &mut state.calculator;
         if arguments.is_empty() {
-            println!("{}", state.calculator.target());
+            for target in calculator.targets() {
+                println!("{target}");
+            }
+            return;
+        }
+        if arguments == "clear" {
+            calculator.clear_targets();
             return;
         }
+        if let Some(item) = arguments.strip_prefix("remove ") {
+            if calculator.remove_target(item.trim()).is_none() {
+                eprintln!("{:?} isn't a target", item.trim());
+            }
+            return;
+        }
+        let (add, arguments) = match arguments.strip_prefix("add ") {
+            Some(arguments) => (true, arguments.trim()),
+            None => (false, arguments),
+        };
         let target = match arguments.parse() {
             Ok(target) => target,
             Err(e) => {
@@ -320,7 +1129,11 @@ impl Command for Target {
                 return;
             }
         };
-        state.calculator.set_target(target);
+        if add {
+            calculator.add_target(target);
+        } else {
+            calculator.set_target(target);
+        }
     }
 
     fn example(&self) -> &'static str {
@@ -328,11 +1141,125 @@ impl Command for Target {
     }
 
     fn short_help(&self) -> &'static str {
-        "Sets the calculator to target `stack` or prints the current target"
+        "Sets the calculator to target `stack` or prints the current targets"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "If `stack` is given, the calculator's targets are replaced by `stack`. Otherwise, ",
+            "prints the calculator's current targets.\n",
+            "`target add <stack>` adds `stack` to the targets, which are all planned together.\n",
+            "`target remove <item>` removes `item` from the targets.\n",
+            "`target clear` removes all of the targets.",
+        )
+    }
+}
+
+struct Throughput;
+
+impl Command for Throughput {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let targets = if arguments.is_empty() {
+            state.calculator.targets().to_vec()
+        } else {
+            match arguments.parse::<Stack>() {
+                Ok(target) => vec![target],
+                Err(e) => {
+                    eprintln!("{e}");
+                    return;
+                }
+            }
+        };
+        let targets = targets
+            .iter()
+            .map(|target| (target.item(), target.count() as f64))
+            .collect::<Vec<_>>();
+        let plan = match state.calculator.rates(&targets) {
+            Ok(plan) => plan,
+            Err(e) => {
+                eprintln!("Couldn't plan rates: {e}");
+                return;
+            }
+        };
+        for step in plan.steps() {
+            let recipe = step.recipe();
+            print!(
+                "{} ({:.2}/min) ({})",
+                recipe.result().item(),
+                step.throughput(),
+                recipe.method(),
+            );
+            match (step.machines(), step.machines_needed()) {
+                (Some(machines), Some(needed)) => println!(": {needed} machines ({machines:.2})"),
+                _ => println!(),
+            }
+        }
+        let machines = plan.machines();
+        if !machines.is_empty() {
+            println!("Machines:");
+            for (method, count) in machines {
+                println!("    {method} ({count})");
+            }
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "throughput [stack]"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "Plans for producing `stack` every minute and prints the machines needed"
+    }
+
+    fn long_help(&self) -> &'static str {
+        concat!(
+            "Plans for continuously producing the count of `stack` every minute and prints the ",
+            "throughput of each step and the number of machines it needs, based on the durations ",
+            "of the recipes. If `stack` is omitted, the counts of the current targets are used ",
+            "as the rates instead.",
+        )
+    }
+}
+
+struct Uses;
+
+impl Command for Uses {
+    fn apply(&self, arguments: &str, state: &mut State) {
+        let (transitive, item) = match arguments.strip_prefix("--all") {
+            Some(item) => (true, item.trim()),
+            None => (false, arguments),
+        };
+        if item.is_empty() {
+            eprintln!("Can't list uses with no `item` argument.");
+            return;
+        }
+        let uses = if transitive {
+            state.calculator.all_uses_of(item)
+        } else {
+            state.calculator.uses_of(item).collect()
+        };
+        if uses.is_empty() {
+            println!("{item:?} isn't used by any recipes");
+        }
+        for recipe in uses {
+            print!("{recipe}");
+        }
+    }
+
+    fn example(&self) -> &'static str {
+        "uses [--all] <item>"
+    }
+
+    fn short_help(&self) -> &'static str {
+        "List the recipes that need `item`"
     }
 
     fn long_help(&self) -> &'static str {
-        "If `stack` is given, the calculator's target is set to `stack`. Otherwise, prints the calculator's current target."
+        concat!(
+            "List the recipes that need `item`, whether they use it up or not. With `--all`, also ",
+            "lists the recipes that need their results and so on, to show everything that `item` ",
+            "eventually feeds into.",
+        )
     }
 }
 
@@ -367,7 +1294,8 @@ impl Command for Write {
             }
         };
         match what {
-            "steps" => write_steps(&mut f, &mut state.calculator),
+            "steps" => write_steps(&mut f, &mut state.calculator, false),
+            "stages" => write_steps(&mut f, &mut state.calculator, true),
             "resources" => write_resources(&mut f, &mut state.calculator),
             "recipes" => write_recipes(&mut f, &mut state.calculator),
             _ => {
@@ -394,19 +1322,33 @@ impl Command for Write {
     fn long_help(&self) -> &'static str {
         concat!(
             "Write the current state of the calculator to `file`.\n",
-            "`what` can be `steps`, `resources`, or `recipes`. ",
+            "`what` can be `steps`, `stages`, `resources`, or `recipes`. ",
             "If `what` is omitted, it is assumed to be `recipes`.",
         )
     }
 }
 
 const COMMANDS: &[(&str, &dyn Command)] = &[
+    ("alternatives", &Alternatives),
+    ("chances", &Chances),
+    ("checklist", &Checklist),
+    ("cost", &Cost),
+    ("craftable", &Craftable),
+    ("cycles", &Cycles),
+    ("done", &Done),
+    ("gather", &Gather),
     ("help", &Help),
     ("load", &Load),
+    ("machines", &Machines),
+    ("max", &Max),
+    ("methods", &Methods),
     ("print", &Print),
     ("recipe", &NewRecipe),
     ("resource", &Resource),
+    ("strategy", &Strategy),
     ("target", &Target),
+    ("throughput", &Throughput),
+    ("uses", &Uses),
     ("write", &Write),
 ];
 
@@ -427,12 +1369,20 @@ fn cli() -> io::Result<()> {
             None => continue,
         };
         let arguments = line.strip_prefix(command).unwrap().trim();
-        match COMMANDS
+        // Commands can be shortened to any prefix that only one of them starts with.
+        let exact = COMMANDS.iter().find(|&&(c, _)| c == command);
+        // Commands that are hard to undo have to be typed out in full.
+        let candidates = COMMANDS
             .iter()
-            .find(|(c, _)| c.strip_prefix(command).is_some())
-        {
-            Some((_, f)) => f.apply(arguments, &mut state),
-            None => Help.apply("", &mut state),
+            .filter(|(c, f)| c.starts_with(command) && !f.needs_full_name())
+            .collect::<Vec<_>>();
+        match (exact, candidates.as_slice()) {
+            (Some((_, f)), _) | (None, [(_, f)]) => f.apply(arguments, &mut state),
+            (None, []) => Help.apply("", &mut state),
+            (None, candidates) => {
+                let names = candidates.iter().map(|&&(c, _)| c).collect::<Vec<_>>();
+                eprintln!("{command:?} could be any of: {}", names.join(", "));
+            }
         }
     }
 }
@@ -440,3 +1390,48 @@ fn cli() -> io::Result<()> {
 fn main() -> io::Result<()> {
     cli()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_steps_by_stage() {
+        let mut calculator = Calculator::new();
+        calculator.add_recipes(vec![
+            Recipe::new(
+                Stack::new("Charcoal", 1),
+                "Furnace",
+                vec![Stack::new("Oak Log", 1)],
+            ),
+            Recipe::new(
+                Stack::new("Oak Wood Planks", 4),
+                "Crafting Table",
+                vec![Stack::new("Oak Log", 1)],
+            ),
+        ]);
+        calculator.add_target(Stack::new("Charcoal", 1));
+        calculator.add_target(Stack::new("Oak Wood Planks", 4));
+        let mut out = vec![];
+        write_steps(&mut out, &mut calculator, true);
+        assert_eq!(
+            concat!(
+                "Stage 1:\n",
+                "Oak Log (2) (Raw Material):\n",
+                "    Oak Log (2)\n",
+                "\n",
+                "Stage 2:\n",
+                "Charcoal (1) (Furnace):\n",
+                "    Oak Log (1)\n",
+                "\n",
+                "Oak Wood Planks (4) (Crafting Table):\n",
+                "    Oak Log (1)\n",
+                "\n",
+            ),
+            String::from_utf8(out).unwrap()
+        );
+        let mut out = vec![];
+        write_steps(&mut out, &mut calculator, false);
+        assert!(!String::from_utf8(out).unwrap().contains("Stage"));
+    }
+}
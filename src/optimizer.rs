@@ -0,0 +1,270 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use minilp::{ComparisonOp, OptimizationDirection, Problem, Variable};
+
+use crate::{Count, Recipe, RecipeFilter, Stack};
+
+/// The cost of getting an item which has a recipe without crafting it. This is large enough that
+/// it is only done when the item can't be crafted at all, like when its recipes form a cycle.
+const UNCRAFTED_PENALTY: f64 = 1e6;
+
+/// A small cost which breaks ties in favor of gathering fewer raw materials and taking more from
+/// storage when the objective doesn't care about either.
+const TIE_BREAKER: f64 = 1e-6;
+
+/// How many times the program is solved again to charge for the catalysts of recipes that
+/// started being used.
+const CATALYST_ROUNDS: usize = 4;
+
+/// The terms that add up to how much of each item is available in a linear program.
+type Rows<'i> = BTreeMap<&'i str, Vec<(Variable, f64)>>;
+
+/// What the optimizer tries to minimize when it chooses between recipes.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Objective {
+    /// Minimize the total number of raw materials that need to be gathered.
+    #[default]
+    RawMaterials,
+    /// Minimize the total cost of the raw materials that need to be gathered, using the costs set
+    /// with [`Calculator::set_raw_cost()`].
+    ///
+    /// [`Calculator::set_raw_cost()`]: crate::Calculator::set_raw_cost
+    WeightedRawMaterials,
+    /// Minimize the total time spent executing recipes. Recipes without a duration take no time.
+    CraftingTime,
+}
+
+/// The inputs to the optimizer.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Optimizer<'c> {
+    pub(crate) recipes: &'c BTreeMap<String, Vec<Arc<Recipe>>>,
+    pub(crate) inventory: &'c BTreeMap<String, Count>,
+    pub(crate) raw_costs: &'c BTreeMap<String, f64>,
+    pub(crate) filter: RecipeFilter<'c>,
+    pub(crate) objective: Objective,
+}
+
+impl<'c> Optimizer<'c> {
+    /// Solves a linear program over every recipe that could be involved in producing each of
+    /// `targets` in the given amount and returns how many times each recipe is executed in the
+    /// cheapest solution, in the same order as the recipes for each item. Since the program isn't
+    /// restricted to whole numbers of executions, the result is only meant to guide the choice of
+    /// recipes. Returns `None` if the program couldn't be solved.
+    pub(crate) fn recipe_usage(
+        &self,
+        targets: &[(&str, f64)],
+    ) -> Option<HashMap<String, Vec<f64>>> {
+        let items = self.relevant_items(targets.iter().map(|&(item, _)| item));
+        // Catalysts are needed once however many times their recipe is executed, which a linear
+        // program can't express. Instead, once a recipe is used, the program is solved again with
+        // its catalysts charged in proportion to its executions, so that executing it as often as
+        // before needs each catalyst once. This repeats while more recipes with catalysts start
+        // being used.
+        let mut used = HashMap::new();
+        for _ in 0..CATALYST_ROUNDS {
+            let usage = self.solve(&items, targets, &used)?;
+            let mut newly_used = false;
+            for (item, executions) in &usage {
+                let recipes = self.recipes.get(item.as_str()).into_iter().flatten();
+                for (idx, (recipe, &executions)) in recipes.zip(executions).enumerate() {
+                    if executions > TIE_BREAKER
+                        && !recipe.catalysts().is_empty()
+                        && !used.contains_key(&(item.clone(), idx))
+                    {
+                        used.insert((item.clone(), idx), executions);
+                        newly_used = true;
+                    }
+                }
+            }
+            if !newly_used {
+                return Some(usage);
+            }
+        }
+        self.solve(&items, targets, &used)
+    }
+
+    /// Solves the program for producing `targets` from `items`, charging for the catalysts of the
+    /// recipes in `used` as if the recipe was executed as often as given there.
+    fn solve(
+        &self,
+        items: &[&str],
+        targets: &[(&str, f64)],
+        used: &HashMap<(String, usize), f64>,
+    ) -> Option<HashMap<String, Vec<f64>>> {
+        let mut problem = Problem::new(OptimizationDirection::Minimize);
+        let (rows, usage_vars) = self.add_items(&mut problem, items, used, true);
+        let mut demand = HashMap::<&str, f64>::new();
+        for &(item, amount) in targets {
+            *demand.entry(item).or_default() += amount;
+        }
+        Self::add_rows(&mut problem, rows, &demand);
+        let solution = problem.solve().ok()?;
+        Some(
+            usage_vars
+                .into_iter()
+                .map(|(item, vars)| {
+                    let usage = vars.into_iter().map(|var| solution[var]).collect();
+                    (item.to_string(), usage)
+                })
+                .collect(),
+        )
+    }
+
+    /// Solves a linear program for the most of `item` that can be produced from the inventory
+    /// alone, without gathering anything. Since the program isn't restricted to whole numbers of
+    /// executions and doesn't hold any catalysts, the result is only an estimate. Returns `None`
+    /// if the program couldn't be solved.
+    pub(crate) fn max_output(&self, item: &str) -> Option<f64> {
+        let items = self.relevant_items([item]);
+        let mut problem = Problem::new(OptimizationDirection::Maximize);
+        let (mut rows, _) = self.add_items(&mut problem, &items, &HashMap::new(), false);
+        let output = problem.add_var(1.0, (0.0, f64::INFINITY));
+        rows.entry(item).or_default().push((output, -1.0));
+        Self::add_rows(&mut problem, rows, &HashMap::new());
+        Some(problem.solve().ok()?[output])
+    }
+
+    /// Adds the variables for getting each of `items` to `problem`, charging for the catalysts of
+    /// the recipes in `used` as if the recipe was executed as often as given there. Items can only
+    /// be gathered if `gather` is set. Returns the terms for how much of each item is available
+    /// and the variables for how many times each recipe is executed.
+    fn add_items<'i>(
+        &self,
+        problem: &mut Problem,
+        items: &[&'i str],
+        used: &HashMap<(String, usize), f64>,
+        gather: bool,
+    ) -> (Rows<'i>, HashMap<&'i str, Vec<Variable>>)
+    where
+        'c: 'i,
+    {
+        let mut rows = Rows::new();
+        let mut usage_vars = HashMap::new();
+        let mut held = BTreeMap::<&str, Variable>::new();
+        for &item in items {
+            let craftable = self.recipes_for(item).next().is_some();
+            let raw_cost = match (craftable, self.objective) {
+                (true, _) => UNCRAFTED_PENALTY,
+                (false, Objective::RawMaterials) => 1.0,
+                (false, Objective::WeightedRawMaterials) => {
+                    self.raw_costs.get(item).copied().unwrap_or(1.0)
+                }
+                (false, Objective::CraftingTime) => TIE_BREAKER,
+            };
+            if gather {
+                let raw = problem.add_var(raw_cost, (0.0, f64::INFINITY));
+                rows.entry(item).or_default().push((raw, 1.0));
+            }
+            if let Some(&available) = self.inventory.get(item).filter(|&&count| count > 0) {
+                let stored = problem.add_var(-TIE_BREAKER, (0.0, available as f64));
+                rows.entry(item).or_default().push((stored, 1.0));
+            }
+            let mut vars = vec![];
+            for (idx, recipe) in self.recipes.get(item).into_iter().flatten().enumerate() {
+                let time = match self.objective {
+                    Objective::CraftingTime => recipe
+                        .duration()
+                        .map_or(0.0, |duration| duration.as_secs_f64()),
+                    _ => 0.0,
+                };
+                // Recipes which may not be used still get a variable so that the usage lines up
+                // with the recipes for the item.
+                let max_executions = if self.filter.allows(item, recipe) {
+                    f64::INFINITY
+                } else {
+                    0.0
+                };
+                let executions = problem.add_var(time, (0.0, max_executions));
+                vars.push(executions);
+                for output in recipe.outputs() {
+                    let produced =
+                        output.count() as f64 * recipe.chance(output.item()).probability();
+                    rows.entry(output.item())
+                        .or_default()
+                        .push((executions, produced));
+                }
+                for ingredient in recipe.ingredients() {
+                    rows.entry(ingredient.item())
+                        .or_default()
+                        .push((executions, -(ingredient.count() as f64)));
+                }
+                for tool in recipe.tools() {
+                    rows.entry(tool.item())
+                        .or_default()
+                        .push((executions, -(tool.uses() as f64 / tool.durability() as f64)));
+                }
+                let Some(&expected) = used.get(&(item.to_string(), idx)) else {
+                    continue;
+                };
+                for catalyst in recipe.catalysts() {
+                    // The catalysts that are held are shared by every recipe that needs them,
+                    // since they aren't used up.
+                    let held = *held.entry(catalyst.item()).or_insert_with(|| {
+                        let held = problem.add_var(0.0, (0.0, f64::INFINITY));
+                        rows.entry(catalyst.item()).or_default().push((held, -1.0));
+                        held
+                    });
+                    problem.add_constraint(
+                        [
+                            (held, 1.0),
+                            (executions, -(catalyst.count() as f64) / expected),
+                        ],
+                        ComparisonOp::Ge,
+                        0.0,
+                    );
+                }
+            }
+            usage_vars.insert(item, vars);
+        }
+        (rows, usage_vars)
+    }
+
+    /// Adds a constraint to `problem` that at least the `demand` for each item is available.
+    fn add_rows(problem: &mut Problem, rows: Rows<'_>, demand: &HashMap<&str, f64>) {
+        for (item, terms) in rows {
+            let mut merged = BTreeMap::<Variable, f64>::new();
+            for (var, coeff) in terms {
+                *merged.entry(var).or_default() += coeff;
+            }
+            let rhs = demand.get(item).copied().unwrap_or_default();
+            problem.add_constraint(
+                merged.into_iter().collect::<Vec<_>>().as_slice(),
+                ComparisonOp::Ge,
+                rhs,
+            );
+        }
+    }
+
+    /// The recipes for `item` which may be used.
+    fn recipes_for<'s>(&'s self, item: &'s str) -> impl Iterator<Item = &'c Arc<Recipe>> + 's {
+        self.recipes
+            .get(item)
+            .into_iter()
+            .flatten()
+            .filter(move |recipe| self.filter.allows(item, recipe))
+    }
+
+    /// Every item that could be involved in producing `targets` with any of the known recipes.
+    fn relevant_items<'t>(&self, targets: impl IntoIterator<Item = &'t str>) -> Vec<&'t str>
+    where
+        'c: 't,
+    {
+        let mut seen = HashSet::new();
+        let mut items = vec![];
+        let mut to_visit = targets.into_iter().collect::<Vec<_>>();
+        while let Some(item) = to_visit.pop() {
+            if !seen.insert(item) {
+                continue;
+            }
+            items.push(item);
+            for recipe in self.recipes_for(item) {
+                to_visit.extend(recipe.requirements());
+                to_visit.extend(recipe.byproducts().iter().map(Stack::item));
+            }
+        }
+        items
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use crate::{Count, Recipe, Stack};
+
+/// A plan for converting the available resources into a set of targets.
+///
+/// The contents of a plan only depend on the recipes, targets, resources and settings it was made
+/// from, so the same inputs always give the same plan in the same order. Items are listed sorted
+/// by name and steps are listed in the order described by [`Plan::steps()`].
+#[derive(Clone, Debug, Default)]
+pub struct Plan {
+    pub(crate) steps: Vec<(Arc<Recipe>, Count)>,
+    pub(crate) stage_starts: Vec<usize>,
+    pub(crate) leftovers: BTreeMap<String, Count>,
+    pub(crate) raw_materials: BTreeMap<String, Count>,
+    pub(crate) withdrawals: BTreeMap<String, Count>,
+    pub(crate) uncraftable: BTreeSet<String>,
+    pub(crate) catalysts: BTreeMap<String, Count>,
+    pub(crate) tool_durability: BTreeMap<String, Count>,
+    pub(crate) cycles: Vec<Vec<String>>,
+}
+
+impl Plan {
+    /// The steps to take, along with how many times each step's recipe is executed. Raw
+    /// materials and withdrawals from storage are included as steps using the "Raw Material"
+    /// and "In Storage" methods.
+    ///
+    /// The steps are grouped into stages, which are listed in the order they need to happen. The
+    /// first stage gathers every raw material, and each stage after that holds the steps whose
+    /// requirements are all produced by earlier stages. Within a stage, steps are sorted by the
+    /// item they produce. Items taken from storage are listed just before the first stage that
+    /// needs them.
+    pub fn steps(&self) -> impl Iterator<Item = (&Recipe, Count)> + '_ {
+        self.steps
+            .iter()
+            .map(|&(ref recipe, count)| (Arc::as_ref(recipe), count))
+    }
+
+    /// The same steps as [`Plan::steps()`], grouped into their stages. The steps in a stage don't
+    /// need anything from each other, so they can all be taken at the same time once the earlier
+    /// stages are done. Items taken from storage are part of the stage before the first stage that
+    /// needs them. Steps whose requirements are never produced are put together in a final stage.
+    pub fn stages(&self) -> impl Iterator<Item = impl Iterator<Item = (&Recipe, Count)> + '_> + '_ {
+        let stage_ends = self
+            .stage_starts
+            .iter()
+            .skip(1)
+            .copied()
+            .chain([self.steps.len()]);
+        self.stage_starts
+            .iter()
+            .zip(stage_ends)
+            .map(|(&start, end)| {
+                self.steps[start..end]
+                    .iter()
+                    .map(|&(ref recipe, count)| (Arc::as_ref(recipe), count))
+            })
+    }
+
+    /// The crafted items that will be left over once every step has been taken, including
+    /// byproducts that weren't needed.
+    pub fn leftovers(&self) -> impl Iterator<Item = Stack> + '_ {
+        stacks(&self.leftovers)
+    }
+
+    /// The total number of each raw material that needs to be gathered.
+    pub fn raw_materials(&self) -> impl Iterator<Item = Stack> + '_ {
+        stacks(&self.raw_materials)
+    }
+
+    /// The total number of each item that is taken from the available resources.
+    pub fn withdrawals(&self) -> impl Iterator<Item = Stack> + '_ {
+        stacks(&self.withdrawals)
+    }
+
+    /// The items which have recipes, but can't be made with the available methods, so they need
+    /// to be gathered as raw materials instead.
+    pub fn uncraftable(&self) -> impl Iterator<Item = &str> + '_ {
+        self.uncraftable.iter().map(String::as_str)
+    }
+
+    /// The catalysts that are needed. Since catalysts aren't used up, each is only needed as many
+    /// times as the single step that needs the most of it.
+    pub fn catalysts(&self) -> impl Iterator<Item = Stack> + '_ {
+        stacks(&self.catalysts)
+    }
+
+    /// The durability that will be left on the partially used tools once every step has been
+    /// taken. Tools that have been completely used up aren't included.
+    pub fn worn_tools(&self) -> impl Iterator<Item = (&str, Count)> + '_ {
+        self.tool_durability
+            .iter()
+            .filter(|&(_, &left)| left > 0)
+            .map(|(name, &left)| (name.as_str(), left))
+    }
+
+    /// The cycles that were found while choosing recipes. Each cycle lists the items involved,
+    /// starting with the item furthest up the recipe tree.
+    pub fn cycles(&self) -> impl Iterator<Item = &[String]> + '_ {
+        self.cycles.iter().map(Vec::as_slice)
+    }
+}
+
+/// The most of an item that can be made from the available resources without gathering anything.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CraftLimit {
+    pub(crate) count: Option<Count>,
+    pub(crate) limited_by: Vec<Stack>,
+}
+
+impl CraftLimit {
+    /// The largest number of the item that can be made, including any that are already
+    /// available, or `None` if making the item doesn't use up any of the resources, so there is no
+    /// limit.
+    pub fn count(&self) -> Option<Count> {
+        self.count
+    }
+
+    /// The resources that run out first, along with how many more of each would need to be
+    /// gathered to make one more of the item. This is empty if there is no limit or if the search
+    /// stopped at a very large count without running out of anything.
+    pub fn limited_by(&self) -> &[Stack] {
+        &self.limited_by
+    }
+}
+
+/// An item that can be crafted from the available resources without gathering anything.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Craftable {
+    pub(crate) limit: CraftLimit,
+    pub(crate) item: String,
+    pub(crate) depth: usize,
+}
+
+impl Craftable {
+    /// The item that can be crafted.
+    pub fn item(&self) -> &str {
+        &self.item
+    }
+
+    /// The most of the item that can be made, including any that are already available, or `None`
+    /// if there is no limit.
+    pub fn count(&self) -> Option<Count> {
+        self.limit.count()
+    }
+
+    /// The most of the item that can be made and the resources that run out first.
+    pub fn limit(&self) -> &CraftLimit {
+        &self.limit
+    }
+
+    /// The number of crafting steps that need to happen one after another to make the item, so
+    /// items made directly from the available resources have a depth of 1.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+fn stacks(counts: &BTreeMap<String, Count>) -> impl Iterator<Item = Stack> + '_ {
+    counts
+        .iter()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(name, &count)| Stack::new(name, count))
+}
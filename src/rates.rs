@@ -0,0 +1,157 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{Count, Recipe, Stack};
+
+/// Rates which are smaller than this are treated as zero to absorb floating point error.
+const EPSILON: f64 = 1e-9;
+
+/// A single step in a plan for producing items at a steady rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateStep {
+    recipe: Arc<Recipe>,
+    executions_per_minute: f64,
+}
+
+impl RateStep {
+    /// The recipe that is executed in this step.
+    pub fn recipe(&self) -> &Recipe {
+        &self.recipe
+    }
+
+    /// The number of times per minute that the recipe needs to be executed.
+    pub fn executions_per_minute(&self) -> f64 {
+        self.executions_per_minute
+    }
+
+    /// The number of [`self.recipe().result()`] that are produced per minute, on average.
+    ///
+    /// [`self.recipe().result()`]: /struct.Recipe.html#method.result
+    pub fn throughput(&self) -> f64 {
+        let result = self.recipe.result();
+        self.executions_per_minute
+            * result.count() as f64
+            * self.recipe.chance(result.item()).probability()
+    }
+
+    /// The number of machines that are needed to keep up with this step if each one is always
+    /// running. This is `None` if the recipe doesn't have a duration.
+    pub fn machines(&self) -> Option<f64> {
+        let duration = self.recipe.duration()?;
+        Some(self.executions_per_minute * duration.as_secs_f64() / 60.0)
+    }
+
+    /// [`self.machines()`] rounded up to a whole number of machines.
+    ///
+    /// [`self.machines()`]: #method.machines
+    pub fn machines_needed(&self) -> Option<Count> {
+        self.machines()
+            .map(|machines| (machines - EPSILON).max(0.0).ceil() as Count)
+    }
+}
+
+/// A plan for producing items at a steady rate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RatePlan {
+    steps: Vec<RateStep>,
+}
+
+impl RatePlan {
+    /// The steps of the plan, with each step coming before the steps that produce its
+    /// ingredients.
+    pub fn steps(&self) -> &[RateStep] {
+        &self.steps
+    }
+
+    /// The total number of machines needed for each crafting method, counting each step's
+    /// machines separately. Steps whose recipes don't have a duration aren't included.
+    pub fn machines(&self) -> BTreeMap<&str, Count> {
+        let mut machines = BTreeMap::new();
+        for step in &self.steps {
+            if let Some(needed) = step.machines_needed() {
+                *machines.entry(step.recipe().method()).or_default() += needed;
+            }
+        }
+        machines
+    }
+}
+
+/// Plans for producing each of `targets` at the given number of items per minute using the
+/// recipes in `recipes`. Items without a recipe are treated as raw materials.
+pub(crate) fn plan(recipes: &HashMap<String, Arc<Recipe>>, targets: &[(&str, f64)]) -> RatePlan {
+    let mut order = vec![];
+    let mut visited = HashSet::new();
+    for &(target, _) in targets {
+        visit(target, recipes, &mut visited, &mut order);
+    }
+    let mut demand = HashMap::<&str, f64>::new();
+    for &(target, rate) in targets {
+        *demand.entry(target).or_default() += rate;
+    }
+    let mut credit = HashMap::<&str, f64>::new();
+    let mut steps = vec![];
+    for &item in order.iter().rev() {
+        let needed = demand.get(item).copied().unwrap_or_default()
+            - credit.get(item).copied().unwrap_or_default();
+        if needed <= EPSILON {
+            continue;
+        }
+        let Some(recipe) = recipes.get(item) else {
+            steps.push(RateStep {
+                recipe: Arc::new(Recipe::new(
+                    Stack::new(item, 1),
+                    "Raw Material",
+                    vec![Stack::new(item, 1)],
+                )),
+                executions_per_minute: needed,
+            });
+            continue;
+        };
+        let per_execution = recipe.result().count() as f64 * recipe.chance(item).probability();
+        let executions = needed / per_execution;
+        for ingredient in recipe.ingredients() {
+            *demand.entry(ingredient.item()).or_default() += ingredient.count() as f64 * executions;
+        }
+        for tool in recipe.tools() {
+            *demand.entry(tool.item()).or_default() +=
+                tool.uses() as f64 * executions / tool.durability() as f64;
+        }
+        for byproduct in recipe.byproducts() {
+            *credit.entry(byproduct.item()).or_default() += byproduct.count() as f64
+                * recipe.chance(byproduct.item()).probability()
+                * executions;
+        }
+        steps.push(RateStep {
+            recipe: Arc::clone(recipe),
+            executions_per_minute: executions,
+        });
+    }
+    RatePlan { steps }
+}
+
+/// Adds `item` to `order` after everything that it depends on. The byproducts of an item's
+/// recipe are treated as depending on the item so that they are credited before they are needed.
+fn visit<'r>(
+    item: &'r str,
+    recipes: &'r HashMap<String, Arc<Recipe>>,
+    visited: &mut HashSet<&'r str>,
+    order: &mut Vec<&'r str>,
+) {
+    if !visited.insert(item) {
+        return;
+    }
+    if let Some(recipe) = recipes.get(item) {
+        for requirement in recipe
+            .ingredients()
+            .iter()
+            .map(Stack::item)
+            .chain(recipe.tools().iter().map(|tool| tool.item()))
+            .chain(recipe.byproducts().iter().map(Stack::item))
+        {
+            visit(requirement, recipes, visited, order);
+        }
+    }
+    order.push(item);
+}
@@ -1,18 +1,30 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
 
 use nom::{
-    branch, bytes::complete as bytes, character::complete as character, combinator, multi,
+    branch, bytes::complete as bytes, character::complete as character, combinator, multi, number,
     sequence, IResult, Parser,
 };
 
-use crate::Stack;
+use crate::{error::Cursor, Chance, ChanceMode, Count, ParseError, RecipeError, Stack, Tool};
+
+/// The methods that the calculator uses for its own steps, which recipes can't use.
+const RESERVED_METHODS: [&str; 2] = ["Raw Material", "In Storage"];
 
 /// A known way to produce a stack from a set of other stacks.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Recipe {
     result: Stack,
+    byproducts: Vec<Stack>,
+    chances: BTreeMap<String, Chance>,
     method: String,
+    duration: Option<Duration>,
     ingredients: Vec<Stack>,
+    catalysts: Vec<Stack>,
+    tools: Vec<Tool>,
 }
 
 impl Recipe {
@@ -21,9 +33,52 @@ impl Recipe {
     pub fn new(result: Stack, method: impl Into<String>, ingredients: Vec<Stack>) -> Self {
         Self {
             result,
+            byproducts: vec![],
+            chances: BTreeMap::new(),
             method: method.into(),
+            duration: None,
             ingredients,
+            catalysts: vec![],
+            tools: vec![],
+        }
+    }
+
+    /// Sets the additional stacks that are produced alongside the result each time this recipe is
+    /// executed.
+    pub fn with_byproducts(mut self, byproducts: Vec<Stack>) -> Self {
+        self.byproducts = byproducts;
+        self
+    }
+
+    /// Sets the chance that `item` is produced each time this recipe is executed. `item` should be
+    /// the result or one of the byproducts.
+    pub fn with_chance(mut self, item: impl Into<String>, chance: Chance) -> Self {
+        let item = item.into();
+        if chance.is_certain() {
+            self.chances.remove(&item);
+        } else {
+            self.chances.insert(item, chance);
         }
+        self
+    }
+
+    /// Sets how long it takes to execute this recipe once.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Sets the stacks that must be present to execute this recipe but which aren't used up by
+    /// it, like tools, molds, or catalysts.
+    pub fn with_catalysts(mut self, catalysts: Vec<Stack>) -> Self {
+        self.catalysts = catalysts;
+        self
+    }
+
+    /// Sets the tools which lose some durability each time this recipe is executed.
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = tools;
+        self
     }
 }
 
@@ -33,15 +88,146 @@ impl Recipe {
         &self.result
     }
 
+    /// The stacks other than the result that are produced by executing this recipe once.
+    pub fn byproducts(&self) -> &[Stack] {
+        &self.byproducts
+    }
+
+    /// The chance that `item` is produced each time this recipe is executed.
+    pub fn chance(&self, item: &str) -> Chance {
+        self.chances.get(item).copied().unwrap_or_default()
+    }
+
+    /// Whether any of the outputs of this recipe are only produced some of the time.
+    pub fn has_uncertain_outputs(&self) -> bool {
+        !self.chances.is_empty()
+    }
+
+    /// The amount of `item` that this recipe can be relied on to produce when it is executed
+    /// `repeats` times and uncertain outputs are planned for according to `mode`.
+    pub fn produced(&self, item: &str, repeats: Count, mode: ChanceMode) -> Count {
+        self.outputs()
+            .filter(|output| output.item() == item)
+            .map(|output| mode.produced(output.count(), self.chance(item), repeats))
+            .sum()
+    }
+
+    /// The result followed by the byproducts, each with the chance that it is produced.
+    pub fn outputs_with_chances(&self) -> impl Iterator<Item = (&Stack, Chance)> + '_ {
+        self.outputs()
+            .map(|output| (output, self.chance(output.item())))
+    }
+
+    /// The result followed by the byproducts.
+    pub(crate) fn outputs(&self) -> impl Iterator<Item = &Stack> + '_ {
+        std::iter::once(&self.result).chain(&self.byproducts)
+    }
+
     /// The method by which the ingredients are turned into the result.
     pub fn method(&self) -> &str {
         &self.method
     }
 
+    /// How long it takes to execute this recipe once, if known.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
     /// The stacks that are required to execute this recipe once.
     pub fn ingredients(&self) -> &[Stack] {
         &self.ingredients
     }
+
+    /// The stacks that must be present to execute this recipe but which aren't used up by it.
+    pub fn catalysts(&self) -> &[Stack] {
+        &self.catalysts
+    }
+
+    /// The tools which lose some durability each time this recipe is executed.
+    pub fn tools(&self) -> &[Tool] {
+        &self.tools
+    }
+
+    /// Every item that must be present to execute this recipe, whether it is used up or not.
+    pub(crate) fn requirements(&self) -> impl Iterator<Item = &str> + '_ {
+        self.ingredients
+            .iter()
+            .chain(&self.catalysts)
+            .map(Stack::item)
+            .chain(self.tools.iter().map(Tool::item))
+    }
+
+    /// Checks that this recipe makes sense, returning the first problem from
+    /// [`self.problems()`] if there are any.
+    ///
+    /// [`self.problems()`]: #method.problems
+    pub fn validate(&self) -> Result<(), RecipeError> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(()),
+        }
+    }
+
+    /// Finds everything that doesn't make sense about this recipe: outputs or requirements with a
+    /// count of 0, not needing anything, needing its own result, listing a requirement more than
+    /// once, and using a method that the calculator uses for its own steps.
+    pub fn problems(&self) -> Vec<RecipeError> {
+        let recipe = self.result.item();
+        let mut problems = vec![];
+        if RESERVED_METHODS.contains(&self.method.as_str()) {
+            problems.push(RecipeError::ReservedMethod {
+                recipe: recipe.to_string(),
+                method: self.method.clone(),
+            });
+        }
+        if self.requirements().next().is_none() {
+            problems.push(RecipeError::NoIngredients {
+                recipe: recipe.to_string(),
+            });
+        }
+        let counts = self
+            .outputs()
+            .chain(&self.ingredients)
+            .chain(&self.catalysts)
+            .map(|stack| (stack.item(), stack.count()))
+            .chain(self.tools.iter().map(|tool| (tool.item(), tool.uses())));
+        for (item, _) in counts.filter(|&(_, count)| count == 0) {
+            problems.push(RecipeError::ZeroCount {
+                recipe: recipe.to_string(),
+                item: item.to_string(),
+            });
+        }
+        if self.requirements().any(|item| item == recipe) {
+            problems.push(RecipeError::SelfReference {
+                recipe: recipe.to_string(),
+            });
+        }
+        let mut seen = BTreeSet::new();
+        for item in self.requirements() {
+            if !seen.insert(item) {
+                problems.push(RecipeError::DuplicateIngredient {
+                    recipe: recipe.to_string(),
+                    item: item.to_string(),
+                });
+            }
+        }
+        let mut seen = BTreeSet::new();
+        for item in self.outputs().map(Stack::item) {
+            if !seen.insert(item) {
+                problems.push(RecipeError::DuplicateOutput {
+                    recipe: recipe.to_string(),
+                    item: item.to_string(),
+                });
+            }
+        }
+        for tool in self.tools.iter().filter(|tool| tool.durability() == 0) {
+            problems.push(RecipeError::ZeroDurability {
+                recipe: recipe.to_string(),
+                tool: tool.item().to_string(),
+            });
+        }
+        problems
+    }
 }
 
 impl Recipe {
@@ -49,22 +235,96 @@ impl Recipe {
         RecipeParser { default_method }
     }
 
+    /// Parses a single output of a recipe and the chance that it is produced, if given.
+    fn nom_parse_output(s: &str) -> IResult<&str, (Stack, Option<Chance>)> {
+        sequence::pair(
+            Stack::nom_parse,
+            combinator::opt(Chance::nom_parse_annotation),
+        )(s)
+    }
+
     /// Parses a list of recipes separated by a blank line.
     pub fn parse_recipes(default_method: &str) -> RecipesParser<'_> {
         RecipesParser { default_method }
     }
+
+    /// Parses every recipe in `s`, like the contents of a recipe file. Recipes which don't specify
+    /// a method use `default_method`. Fails with the position of the first recipe that couldn't be
+    /// parsed.
+    pub fn parse_all(s: &str, default_method: &str) -> Result<Vec<Self>, ParseError> {
+        let (recipes, errors) = Self::parse_all_with_lines(s, default_method);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(recipes.into_iter().map(|(_, recipe)| recipe).collect()),
+        }
+    }
+
+    /// Like [`Recipe::parse_all()`], but also returns the line where each recipe starts. Recipes
+    /// that can't be parsed are skipped up to the next line that starts a recipe, and the position
+    /// where each of them failed is returned alongside the recipes that could be parsed.
+    pub(crate) fn parse_all_with_lines(
+        s: &str,
+        default_method: &str,
+    ) -> (Vec<(usize, Self)>, Vec<ParseError>) {
+        let mut recipes = vec![];
+        let mut errors = vec![];
+        let mut cursor = Cursor::new(s);
+        let mut rest = s.trim_start();
+        while !rest.is_empty() {
+            let (line, _) = cursor.position(rest);
+            match Self::nom_parse(default_method).parse(rest) {
+                Ok((remaining, recipe)) => {
+                    recipes.push((line, recipe));
+                    rest = remaining;
+                }
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                    errors.push(ParseError::at(cursor.position(e.input), "a recipe"));
+                    rest = Self::skip_recipe(rest);
+                }
+                Err(nom::Err::Incomplete(_)) => {
+                    errors.push(ParseError::at(cursor.position(rest), "a recipe"));
+                    rest = Self::skip_recipe(rest);
+                }
+            }
+            rest = rest.trim_start();
+        }
+        (recipes, errors)
+    }
+
+    /// Skips the rest of the line at the start of `s` and any indented lines after it, which
+    /// belong to the same recipe.
+    fn skip_recipe(s: &str) -> &str {
+        let mut rest = s;
+        loop {
+            rest = match rest.find('\n') {
+                Some(idx) => &rest[idx + 1..],
+                None => return "",
+            };
+            if !rest.starts_with(char::is_whitespace) {
+                return rest;
+            }
+        }
+    }
 }
 
 impl Display for Recipe {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let repeats = f.precision().unwrap_or(1);
-        writeln!(
-            f,
-            "{} ({}) ({}):",
-            self.result().item(),
-            self.result().count() * repeats,
-            self.method(),
-        )?;
+        for (idx, output) in self.outputs().enumerate() {
+            if idx > 0 {
+                write!(f, " + ")?;
+            }
+            write!(f, "{} ({})", output.item(), output.count() * repeats)?;
+            let chance = self.chance(output.item());
+            if !chance.is_certain() {
+                write!(f, " [{chance}]")?;
+            }
+        }
+        write!(f, " ({})", self.method())?;
+        if let Some(duration) = self.duration() {
+            write!(f, " [{}s]", duration.as_secs_f64())?;
+        }
+        writeln!(f, ":")?;
         for ingredient in self.ingredients() {
             writeln!(
                 f,
@@ -73,10 +333,44 @@ impl Display for Recipe {
                 ingredient.count() * repeats
             )?;
         }
+        for catalyst in self.catalysts() {
+            writeln!(
+                f,
+                "    {} ({}) [catalyst]",
+                catalyst.item(),
+                catalyst.count()
+            )?;
+        }
+        for tool in self.tools() {
+            writeln!(f, "    {tool:.repeats$}")?;
+        }
         Ok(())
     }
 }
 
+/// A single entry in a recipe's list of ingredients.
+#[derive(Clone, Debug)]
+enum Ingredient {
+    Consumed(Stack),
+    Catalyst(Stack),
+    Tool(Tool),
+}
+
+impl Ingredient {
+    fn nom_parse(s: &str) -> IResult<&str, Self> {
+        branch::alt((
+            combinator::map(Tool::nom_parse, Self::Tool),
+            combinator::map(
+                sequence::pair(Stack::nom_parse, combinator::opt(bytes::tag(" [catalyst]"))),
+                |(stack, catalyst)| match catalyst {
+                    Some(_) => Self::Catalyst(stack),
+                    None => Self::Consumed(stack),
+                },
+            ),
+        ))(s)
+    }
+}
+
 /// A parser for a single recipe.
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct RecipeParser<'d> {
@@ -89,23 +383,38 @@ where
 {
     fn parse(&mut self, s: &'i str) -> IResult<&'i str, Recipe> {
         let result_and_method = sequence::pair(
-            Stack::nom_parse,
-            sequence::terminated(
-                combinator::opt(sequence::delimited(
-                    bytes::tag(" ("),
-                    combinator::recognize(multi::many1(character::none_of(")"))),
-                    bytes::tag(")"),
+            sequence::pair(
+                Recipe::nom_parse_output,
+                multi::many0(sequence::preceded(
+                    bytes::tag(" + "),
+                    Recipe::nom_parse_output,
                 )),
+            ),
+            sequence::terminated(
+                sequence::pair(
+                    combinator::opt(sequence::delimited(
+                        bytes::tag(" ("),
+                        combinator::recognize(multi::many1(character::none_of(")"))),
+                        bytes::tag(")"),
+                    )),
+                    combinator::opt(sequence::delimited(
+                        bytes::tag(" ["),
+                        combinator::map_opt(number::complete::double, |secs| {
+                            Duration::try_from_secs_f64(secs).ok()
+                        }),
+                        bytes::tag("s]"),
+                    )),
+                ),
                 bytes::tag(":"),
             ),
         );
         let single_ingredient = combinator::map(
-            sequence::preceded(bytes::tag(" "), Stack::nom_parse),
+            sequence::preceded(bytes::tag(" "), Ingredient::nom_parse),
             |ingredient| vec![ingredient],
         );
         let multiple_ingredients = multi::many1(sequence::preceded(
             sequence::pair(character::line_ending, character::space1),
-            Stack::nom_parse,
+            Ingredient::nom_parse,
         ));
         combinator::map(
             sequence::pair(
@@ -115,10 +424,37 @@ where
                     character::line_ending,
                 ),
             ),
-            |((result, method), ingredients)| Recipe {
-                result,
-                method: method.unwrap_or(self.default_method).to_string(),
-                ingredients,
+            |(((result, byproducts), (method, duration)), all_ingredients)| {
+                let (result, result_chance) = result;
+                let chances = std::iter::once((result.item().to_string(), result_chance))
+                    .chain(
+                        byproducts
+                            .iter()
+                            .map(|(stack, chance)| (stack.item().to_string(), *chance)),
+                    )
+                    .filter_map(|(item, chance)| Some((item, chance?)))
+                    .collect();
+                let byproducts = byproducts.into_iter().map(|(stack, _)| stack).collect();
+                let mut ingredients = vec![];
+                let mut catalysts = vec![];
+                let mut tools = vec![];
+                for ingredient in all_ingredients {
+                    match ingredient {
+                        Ingredient::Consumed(stack) => ingredients.push(stack),
+                        Ingredient::Catalyst(stack) => catalysts.push(stack),
+                        Ingredient::Tool(tool) => tools.push(tool),
+                    }
+                }
+                Recipe {
+                    result,
+                    byproducts,
+                    chances,
+                    method: method.unwrap_or(self.default_method).to_string(),
+                    duration,
+                    ingredients,
+                    catalysts,
+                    tools,
+                }
             },
         )(s)
     }
@@ -146,6 +482,12 @@ mod tests {
     const ONE_LINE_NO_METHOD: &str = "Oak Wood Planks (4): Oak Log (1)\n";
     const ONE_LINE_WITH_METHOD: &str = "Charcoal (1) (Furnace): Oak Log (1)\n";
     const MULTI_LINE: &str = "Wooden Shovel (1):\n Oak Wood Planks (1)\n Stick (2)\n";
+    const BYPRODUCTS: &str = "Iron Ingot (1) + Slag (1) (Furnace): Iron Ore (1)\n";
+    const CHANCES: &str = "Iron Dust (2) + Nickel Dust (1) [12.5%] (Pulverizer): Iron Ore (1)\n";
+    const DURATION: &str = "Iron Ingot (1) (Furnace) [3.2s]: Iron Ore (1)\n";
+    const TOOLS: &str = "Raw Beef (1) (Butchering):\n Cow Carcass (1)\n Knife (2) [tool 64]\n";
+    const CATALYSTS: &str =
+        "Iron Plate (1) (Press):\n Iron Ingot (1)\n Plate Mold (1) [catalyst]\n";
 
     #[test]
     fn parse_one_line_recipe_implicit_method() {
@@ -153,8 +495,13 @@ mod tests {
             "",
             Recipe {
                 result: Stack::new("Oak Wood Planks", 4),
+                byproducts: vec![],
+                chances: BTreeMap::new(),
                 method: "Crafting Table".to_string(),
+                duration: None,
                 ingredients: vec![Stack::new("Oak Log", 1)],
+                catalysts: vec![],
+                tools: vec![],
             },
         );
         let actual = Recipe::nom_parse("Crafting Table")
@@ -169,8 +516,13 @@ mod tests {
             "",
             Recipe {
                 result: Stack::new("Charcoal", 1),
+                byproducts: vec![],
+                chances: BTreeMap::new(),
                 method: "Furnace".to_string(),
+                duration: None,
                 ingredients: vec![Stack::new("Oak Log", 1)],
+                catalysts: vec![],
+                tools: vec![],
             },
         );
         let actual = Recipe::nom_parse("Crafting Table")
@@ -185,8 +537,13 @@ mod tests {
             "",
             Recipe {
                 result: Stack::new("Wooden Shovel", 1),
+                byproducts: vec![],
+                chances: BTreeMap::new(),
                 method: "Crafting Table".to_string(),
+                duration: None,
                 ingredients: vec![Stack::new("Oak Wood Planks", 1), Stack::new("Stick", 2)],
+                catalysts: vec![],
+                tools: vec![],
             },
         );
         let actual = Recipe::nom_parse("Crafting Table")
@@ -194,4 +551,196 @@ mod tests {
             .unwrap();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn parse_recipe_with_byproducts() {
+        let expected = (
+            "",
+            Recipe {
+                result: Stack::new("Iron Ingot", 1),
+                byproducts: vec![Stack::new("Slag", 1)],
+                chances: BTreeMap::new(),
+                method: "Furnace".to_string(),
+                duration: None,
+                ingredients: vec![Stack::new("Iron Ore", 1)],
+                catalysts: vec![],
+                tools: vec![],
+            },
+        );
+        let actual = Recipe::nom_parse("Crafting Table")
+            .parse(BYPRODUCTS)
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn display_recipe_with_byproducts() {
+        let recipe = Recipe::nom_parse("Crafting Table")
+            .parse(BYPRODUCTS)
+            .unwrap()
+            .1;
+        assert_eq!(
+            "Iron Ingot (2) + Slag (2) (Furnace):\n    Iron Ore (2)\n",
+            format!("{recipe:.2}"),
+        );
+    }
+
+    #[test]
+    fn parse_recipe_with_catalysts() {
+        let expected = (
+            "",
+            Recipe {
+                result: Stack::new("Iron Plate", 1),
+                byproducts: vec![],
+                chances: BTreeMap::new(),
+                method: "Press".to_string(),
+                duration: None,
+                ingredients: vec![Stack::new("Iron Ingot", 1)],
+                catalysts: vec![Stack::new("Plate Mold", 1)],
+                tools: vec![],
+            },
+        );
+        let actual = Recipe::nom_parse("Crafting Table")
+            .parse(CATALYSTS)
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn display_recipe_with_catalysts() {
+        let recipe = Recipe::nom_parse("Crafting Table")
+            .parse(CATALYSTS)
+            .unwrap()
+            .1;
+        assert_eq!(
+            "Iron Plate (3) (Press):\n    Iron Ingot (3)\n    Plate Mold (1) [catalyst]\n",
+            format!("{recipe:.3}"),
+        );
+    }
+
+    #[test]
+    fn parse_recipe_with_tools() {
+        let expected = (
+            "",
+            Recipe {
+                result: Stack::new("Raw Beef", 1),
+                byproducts: vec![],
+                chances: BTreeMap::new(),
+                method: "Butchering".to_string(),
+                duration: None,
+                ingredients: vec![Stack::new("Cow Carcass", 1)],
+                catalysts: vec![],
+                tools: vec![Tool::new("Knife", 2, 64)],
+            },
+        );
+        let actual = Recipe::nom_parse("Crafting Table").parse(TOOLS).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn parse_recipe_with_chances() {
+        let expected = (
+            "",
+            Recipe::new(
+                Stack::new("Iron Dust", 2),
+                "Pulverizer",
+                vec![Stack::new("Iron Ore", 1)],
+            )
+            .with_byproducts(vec![Stack::new("Nickel Dust", 1)])
+            .with_chance("Nickel Dust", Chance::from_percent(12.5)),
+        );
+        let actual = Recipe::nom_parse("Crafting Table").parse(CHANCES).unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(CHANCES.replace(": ", ":\n    "), actual.1.to_string());
+    }
+
+    #[test]
+    fn parse_recipe_with_duration() {
+        let expected = (
+            "",
+            Recipe::new(
+                Stack::new("Iron Ingot", 1),
+                "Furnace",
+                vec![Stack::new("Iron Ore", 1)],
+            )
+            .with_duration(Duration::from_millis(3200)),
+        );
+        let actual = Recipe::nom_parse("Crafting Table").parse(DURATION).unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(DURATION.replace(": ", ":\n    "), actual.1.to_string());
+    }
+
+    #[test]
+    fn parse_recipe_file() {
+        let recipes = format!("{ONE_LINE_WITH_METHOD}{MULTI_LINE}");
+        assert_eq!(
+            2,
+            Recipe::parse_all(&recipes, "Crafting Table").unwrap().len()
+        );
+        let error = Recipe::parse_all(
+            &format!("{ONE_LINE_WITH_METHOD}Stick (4) (Crafting Table) Oak Planks (2)\n"),
+            "Crafting Table",
+        )
+        .unwrap_err();
+        assert_eq!((2, 27), (error.line(), error.column()));
+    }
+
+    #[test]
+    fn skip_unparsable_recipes() {
+        let recipes = format!(
+            "Stick (4) Oak Planks (2)\n{MULTI_LINE}Wooden Hoe (1):\n Stick (two)\n\n{ONE_LINE_WITH_METHOD}"
+        );
+        let (recipes, errors) = Recipe::parse_all_with_lines(&recipes, "Crafting Table");
+        assert_eq!(
+            vec![2, 8],
+            recipes.iter().map(|(line, _)| *line).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(1, 10), (6, 9)],
+            errors
+                .iter()
+                .map(|error| (error.line(), error.column()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn validate_recipe() {
+        let recipe = Recipe::nom_parse("Crafting Table")
+            .parse(MULTI_LINE)
+            .unwrap()
+            .1;
+        assert_eq!(Ok(()), recipe.validate());
+        let recipe = Recipe::new(
+            Stack::new("Stick", 4),
+            "Crafting Table",
+            vec![Stack::new("Oak Wood Planks", 0)],
+        );
+        assert_eq!(
+            Err(RecipeError::ZeroCount {
+                recipe: "Stick".to_string(),
+                item: "Oak Wood Planks".to_string()
+            }),
+            recipe.validate()
+        );
+        let recipe = Recipe::nom_parse("Crafting Table")
+            .parse(
+                "Iron Ingot (1) + Iron Ingot (1) (Furnace):\n Iron Ore (1)\n Tongs (1) [tool 0]\n",
+            )
+            .unwrap()
+            .1;
+        assert_eq!(
+            vec![
+                RecipeError::DuplicateOutput {
+                    recipe: "Iron Ingot".to_string(),
+                    item: "Iron Ingot".to_string()
+                },
+                RecipeError::ZeroDurability {
+                    recipe: "Iron Ingot".to_string(),
+                    tool: "Tongs".to_string()
+                },
+            ],
+            recipe.problems()
+        );
+    }
 }
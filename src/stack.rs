@@ -8,6 +8,8 @@ use nom::{
     sequence, IResult,
 };
 
+use crate::ParseError;
+
 /// The number of items in a stack.
 pub type Count = usize;
 
@@ -62,7 +64,7 @@ impl Display for Stack {
 }
 
 impl FromStr for Stack {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         use nom::Finish;
@@ -70,6 +72,6 @@ impl FromStr for Stack {
         Self::nom_parse(s)
             .finish()
             .map(|(_, stack)| stack)
-            .map_err(|e| format!("Couldn't parse stack: {e:?}"))
+            .map_err(|e| ParseError::new(s, e.input, "a stack like `Oak Log (1)`"))
     }
 }
@@ -0,0 +1,91 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use nom::{bytes::complete as bytes, combinator as comb, sequence, IResult};
+
+use crate::{Count, ParseError, Stack};
+
+/// A tool which loses some of its durability each time a recipe is executed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tool {
+    name: String,
+    uses: Count,
+    durability: Count,
+}
+
+impl Tool {
+    /// Makes a new tool called `name` which can be used `durability` times before it breaks and
+    /// which is used `uses` times each time the recipe is executed.
+    pub fn new(name: impl Into<String>, uses: Count, durability: Count) -> Self {
+        Self {
+            name: name.into(),
+            uses,
+            durability,
+        }
+    }
+}
+
+impl Tool {
+    /// The item that is used as the tool.
+    pub fn item(&self) -> &str {
+        &self.name
+    }
+
+    /// The amount of durability that is used up each time the recipe is executed.
+    pub fn uses(&self) -> Count {
+        self.uses
+    }
+
+    /// The number of uses a new tool has before it breaks.
+    pub fn durability(&self) -> Count {
+        self.durability
+    }
+}
+
+impl Tool {
+    pub(crate) fn nom_parse(s: &str) -> IResult<&str, Self> {
+        comb::map(
+            sequence::pair(
+                Stack::nom_parse,
+                sequence::delimited(
+                    bytes::tag(" [tool "),
+                    crate::util::read_usize,
+                    bytes::tag("]"),
+                ),
+            ),
+            |(stack, durability)| Self {
+                name: stack.item().to_string(),
+                uses: stack.count(),
+                durability,
+            },
+        )(s)
+    }
+}
+
+impl Display for Tool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let repeats = f.precision().unwrap_or(1);
+        write!(
+            f,
+            "{} ({}) [tool {}]",
+            self.item(),
+            self.uses() * repeats,
+            self.durability()
+        )
+    }
+}
+
+impl FromStr for Tool {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use nom::Finish;
+
+        Self::nom_parse(s)
+            .finish()
+            .map(|(_, tool)| tool)
+            .map_err(|e| ParseError::new(s, e.input, "a tool like `Knife (1) [tool 64]`"))
+    }
+}